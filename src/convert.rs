@@ -0,0 +1,87 @@
+//! Conversions of Litchi missions to other formats, without going through a flight plan.
+
+use std::{ffi::OsStr, fs, path::Path};
+
+use crate::{
+    error::Error,
+    litchi::{self, bin::LitchiMission, csv::de::MissionRecord},
+    wpml::{self, Aircraft},
+};
+
+/// Converts the CSV or bin mission at `input` to the format told by the extension
+/// of `output`: `.kmz` for DJI WPML flown by `aircraft`, `.csv`, `.bin` or `.kml` for Litchi.
+pub fn run(input: &Path, output: &Path, aircraft: Aircraft) -> Result<(), Error> {
+    let data = fs::read(input)?;
+
+    let bin = extension(input) == "bin";
+
+    // Both shapes of the mission, the one read and the one converted from it.
+    let (mission, records) = if bin {
+        let mission = litchi::bin::from_slice(&data)?;
+        let records = Vec::<MissionRecord>::from(&mission);
+
+        (mission, records)
+    } else {
+        let records: Vec<MissionRecord> = csv::Reader::from_reader(data.as_slice())
+            .deserialize()
+            .collect::<Result<_, _>>()?;
+
+        (LitchiMission::from(records.as_slice()), records)
+    };
+
+    let format = extension(output);
+
+    // What is lost going from one Litchi format to the other
+    let losses = match (bin, format.as_str()) {
+        (true, "csv" | "kml") => mission.csv_losses(),
+        (false, "bin") => LitchiMission::bin_losses(&records),
+        _ => vec![],
+    };
+
+    for loss in losses {
+        eprintln!("{}: {}", input.display(), loss);
+    }
+
+    match format.as_str() {
+        "kmz" => {
+            let wpml = if bin {
+                wpml::Mission::from(&mission)
+            } else {
+                wpml::Mission::from(records.as_slice())
+            };
+
+            for loss in wpml.losses() {
+                eprintln!("{}: {}", input.display(), loss);
+            }
+
+            wpml.to_kmz(aircraft, fs::File::create(output)?)
+        }
+
+        "csv" => Ok(litchi::csv::ser::to_writer(
+            &records,
+            fs::File::create(output)?,
+        )?),
+
+        "bin" => Ok(fs::write(output, litchi::bin::to_vec(&mission))?),
+
+        "kml" => {
+            let name = input
+                .file_stem()
+                .and_then(OsStr::to_str)
+                .unwrap_or_default();
+
+            let kml = litchi::kml::to_kml(name, &records);
+
+            litchi::kml::to_writer(&kml, fs::File::create(output)?)
+        }
+
+        _ => Err(Error::UnsupportedConversion(output.to_path_buf())),
+    }
+}
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .and_then(OsStr::to_str)
+        .unwrap_or_default()
+        .to_ascii_lowercase()
+}
@@ -0,0 +1,344 @@
+use std::{
+    fs,
+    io::{self, BufRead, Write},
+    path::PathBuf,
+};
+
+use chrono::{Local, TimeZone, Utc};
+use clap::{ArgGroup, Subcommand, ValueEnum};
+use regex::Regex;
+use serde::Serialize;
+
+use crate::{
+    error::Error,
+    flightplan::{sanitize_file_name, FlightPlan},
+    litchi::{self, csv::de::MissionRecord},
+    mtp,
+    store::{self, FlightPlanStore, Location, StoredFlightPlan, SAVED_PLAN_FILE_NAME},
+};
+
+const MS_PER_DAY: u64 = 24 * 60 * 60 * 1000;
+
+#[derive(Subcommand, Debug)]
+pub enum DeviceCommand {
+    /// List the MTP devices plugged in, to pick one with `--device`
+    Ls,
+
+    /// List flight plans stored on the device
+    List {
+        /// Print as JSON instead of a table
+        #[clap(long)]
+        json: bool,
+    },
+
+    /// Download flight plans from the device, as `flightPlan/<uuid>/savedPlan.json`
+    Pull {
+        /// UUID or title of the plan to download, all plans by default
+        selector: Option<String>,
+
+        /// Destination directory
+        #[clap(short, long, default_value = ".")]
+        output: PathBuf,
+
+        /// Also convert each plan to a Litchi mission, next to its `savedPlan.json`
+        #[clap(long, value_enum)]
+        convert: Option<LitchiFormat>,
+    },
+
+    /// Delete flight plans from the device
+    Rm {
+        /// UUID or title of the plan to delete
+        selector: String,
+
+        /// Don't ask for confirmation
+        #[clap(short, long)]
+        yes: bool,
+    },
+
+    /// Delete every flight plan matching all the given filters
+    #[clap(group(ArgGroup::new("filter").required(true).multiple(true)))]
+    Prune {
+        /// Plans whose title matches this regular expression
+        #[clap(long, group = "filter")]
+        title: Option<Regex>,
+
+        /// Plans created more than this many days ago
+        #[clap(long, group = "filter")]
+        older_than: Option<u64>,
+
+        /// Plans converted by litchi2fp, recognized by their content derived UUID:
+        /// those converted with `--random-uuid`, or by older versions, are not
+        #[clap(long, group = "filter")]
+        converted: bool,
+
+        /// Don't ask for confirmation
+        #[clap(short, long)]
+        yes: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum LitchiFormat {
+    Csv,
+    Kml,
+}
+
+pub fn run(command: &DeviceCommand, location: &Location) -> Result<(), Error> {
+    match command {
+        DeviceCommand::Ls => list_devices()?,
+
+        DeviceCommand::List { json } => {
+            let mut store = location.open()?;
+
+            let stored = store::list_flightplans(store.as_mut())?;
+
+            let summaries: Vec<_> = stored
+                .iter()
+                .map(|s| Summary::from(&s.flightplan))
+                .collect();
+
+            if *json {
+                let json =
+                    serde_json::to_string_pretty(&summaries).map_err(Error::JsonWritingFailed)?;
+
+                println!("{}", json);
+            } else {
+                print_table(&summaries);
+            }
+        }
+
+        DeviceCommand::Pull {
+            selector,
+            output,
+            convert,
+        } => {
+            let mut store = location.open()?;
+
+            let mut stored = store::list_flightplans(store.as_mut())?;
+
+            if let Some(selector) = selector {
+                stored.retain(|s| s.matches(selector));
+
+                if stored.is_empty() {
+                    return Err(Error::FlightPlanNotFound(selector.clone()));
+                }
+            }
+
+            for s in stored {
+                let folder = output.join(crate::output::plan_folder(&s.flightplan));
+
+                fs::create_dir_all(&folder)?;
+
+                fs::write(folder.join(SAVED_PLAN_FILE_NAME), &s.data)?;
+
+                if let Some(format) = convert {
+                    convert_to_litchi(&s.flightplan, &folder, *format)?;
+                }
+
+                println!(
+                    "Pulled '{}' ({}) to {}",
+                    s.flightplan.title,
+                    s.flightplan.uuid,
+                    folder.display()
+                );
+            }
+        }
+
+        DeviceCommand::Rm { selector, yes } => {
+            let mut store = location.open()?;
+
+            let mut stored = store::list_flightplans(store.as_mut())?;
+
+            stored.retain(|s| s.matches(selector));
+
+            if stored.is_empty() {
+                return Err(Error::FlightPlanNotFound(selector.clone()));
+            }
+
+            delete(store.as_mut(), &stored, *yes)?;
+        }
+
+        DeviceCommand::Prune {
+            title,
+            older_than,
+            converted,
+            yes,
+        } => {
+            let mut store = location.open()?;
+
+            let mut stored = store::list_flightplans(store.as_mut())?;
+
+            let now = Utc::now().timestamp_millis() as u64;
+
+            stored.retain(|s| {
+                let plan = &s.flightplan;
+
+                let title_matches = title.as_ref().map_or(true, |re| re.is_match(&plan.title));
+
+                let old_enough = older_than.map_or(true, |days| {
+                    now.saturating_sub(plan.date) > days * MS_PER_DAY
+                });
+
+                title_matches && old_enough && (!converted || plan.is_converted())
+            });
+
+            if stored.is_empty() {
+                println!("No flight plan to delete.");
+            } else {
+                delete(store.as_mut(), &stored, *yes)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn list_devices() -> Result<(), Error> {
+    let devices = mtp::detect_devices()?;
+
+    if devices.is_empty() {
+        println!("No MTP device detected.");
+
+        return Ok(());
+    }
+
+    println!(
+        "{:<7}  {:<9}  {:<20}  {:<24}  SERIAL",
+        "BUS:DEV", "VID:PID", "VENDOR", "PRODUCT"
+    );
+
+    for device in devices {
+        let name = |name: &str| match name {
+            "" => "unknown".to_owned(),
+            name => name.to_owned(),
+        };
+
+        println!(
+            "{:03}:{:03}  {:04x}:{:04x}  {:<20}  {:<24}  {}",
+            device.bus_number,
+            device.dev_number,
+            device.entry.vendor_id,
+            device.entry.product_id,
+            name(device.entry.vendor),
+            name(device.entry.product),
+            device.serial_number.as_deref().unwrap_or("-")
+        );
+    }
+
+    Ok(())
+}
+
+fn delete(
+    store: &mut dyn FlightPlanStore,
+    stored: &[StoredFlightPlan],
+    yes: bool,
+) -> Result<(), Error> {
+    let summaries: Vec<_> = stored
+        .iter()
+        .map(|s| Summary::from(&s.flightplan))
+        .collect();
+
+    print_table(&summaries);
+
+    if !yes && !confirm(&format!("Delete {} flight plan(s)?", stored.len()))? {
+        return Ok(());
+    }
+
+    for s in stored {
+        store::delete_flightplan(store, s)?;
+
+        println!("Deleted '{}' ({})", s.flightplan.title, s.flightplan.uuid);
+    }
+
+    Ok(())
+}
+
+fn confirm(question: &str) -> Result<bool, Error> {
+    print!("{} [y/N] ", question);
+
+    io::stdout().flush()?;
+
+    let mut answer = String::new();
+
+    io::stdin().lock().read_line(&mut answer)?;
+
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+fn convert_to_litchi(
+    flightplan: &FlightPlan,
+    folder: &std::path::Path,
+    format: LitchiFormat,
+) -> Result<(), Error> {
+    let records = Vec::<MissionRecord>::from(flightplan);
+
+    let name = sanitize_file_name(&flightplan.title);
+
+    match format {
+        LitchiFormat::Csv => {
+            let file = fs::File::create(folder.join(name + ".csv"))?;
+
+            litchi::csv::ser::to_writer(&records, file)?;
+        }
+
+        LitchiFormat::Kml => {
+            let file = fs::File::create(folder.join(name + ".kml"))?;
+
+            let kml = litchi::kml::to_kml(&flightplan.title, &records);
+
+            litchi::kml::to_writer(&kml, file)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct Summary<'a> {
+    title: &'a str,
+    uuid: &'a str,
+    date: String,
+    waypoints: usize,
+
+    /// In meters
+    distance: f64,
+}
+
+impl<'a> From<&'a FlightPlan<'_>> for Summary<'a> {
+    fn from(flightplan: &'a FlightPlan<'_>) -> Self {
+        let date = Local
+            .timestamp_millis_opt(flightplan.date as i64)
+            .single()
+            .map(|date| date.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_default();
+
+        Summary {
+            title: &flightplan.title,
+            uuid: &flightplan.uuid,
+            date,
+            waypoints: flightplan.plan.waypoints.len(),
+            distance: flightplan.distance().round(),
+        }
+    }
+}
+
+fn print_table(summaries: &[Summary]) {
+    let title_width = summaries
+        .iter()
+        .map(|s| s.title.chars().count())
+        .chain(Some("TITLE".len()))
+        .max()
+        .unwrap_or_default();
+
+    println!(
+        "{:<title_width$}  {:<36}  {:<16}  {:>9}  {:>8}",
+        "TITLE", "UUID", "DATE", "WAYPOINTS", "DISTANCE"
+    );
+
+    for s in summaries {
+        println!(
+            "{:<title_width$}  {:<36}  {:<16}  {:>9}  {:>7}m",
+            s.title, s.uuid, s.date, s.waypoints, s.distance
+        );
+    }
+}
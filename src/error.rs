@@ -6,11 +6,35 @@ pub enum Error {
     KmlParsingFailed(kml::Error),
     CsvParsingFailed(csv::Error),
     BinParsingFailed(litchi::bin::Error),
+    ProfileParsingFailed(serde_json::Error),
     MtpFailure(crate::mtp::MtpError),
+    FtpFailure(crate::ftp::FtpError),
     MalformedLitchiMission(&'static str),
+    GeoJsonParsingFailed(serde_json::Error),
+    JsonWritingFailed(serde_json::Error),
+    MalformedGeoJson(&'static str),
+    GpxParsingFailed(quick_xml::Error),
+    MalformedGpx(&'static str),
+
+    /// Line of the waypoint file that couldn't be read
+    MalformedWaypointFile(usize),
+
+    NoWaypoint,
     AltitudeOverflow(std::num::IntErrorKind),
+    TooManyPointsOfInterest(usize),
     MissingTitle,
+    FlightPlanNotFound(String),
+    FlightPlanConflict(String),
     InvalidFileName,
+    NoFreeFlight6Directory(std::path::PathBuf),
+    SeveralPlansInOneFile(usize),
+    ZipFailure(zip::result::ZipError),
+
+    /// Export needing altitudes above sea level, see `--takeoff-elevation`
+    NoTakeoffElevation,
+
+    /// Output whose extension names no format the mission converts to
+    UnsupportedConversion(std::path::PathBuf),
 }
 
 impl From<kml::Error> for Error {
@@ -23,6 +47,16 @@ impl From<kml::Error> for Error {
     }
 }
 
+impl From<quick_xml::Error> for Error {
+    fn from(underlying: quick_xml::Error) -> Self {
+        match underlying {
+            quick_xml::Error::Io(e) => Error::InputOutput(e),
+
+            _ => Error::GpxParsingFailed(underlying),
+        }
+    }
+}
+
 impl From<csv::Error> for Error {
     fn from(e: csv::Error) -> Self {
         Error::CsvParsingFailed(e)
@@ -41,6 +75,22 @@ impl From<crate::mtp::MtpError> for Error {
     }
 }
 
+impl From<crate::ftp::FtpError> for Error {
+    fn from(underlying: crate::ftp::FtpError) -> Self {
+        Error::FtpFailure(underlying)
+    }
+}
+
+impl From<zip::result::ZipError> for Error {
+    fn from(underlying: zip::result::ZipError) -> Self {
+        match underlying {
+            zip::result::ZipError::Io(e) => Error::InputOutput(e),
+
+            _ => Error::ZipFailure(underlying),
+        }
+    }
+}
+
 impl From<litchi::bin::Error> for Error {
     fn from(underlying: litchi::bin::Error) -> Self {
         Error::BinParsingFailed(underlying)
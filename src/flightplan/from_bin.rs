@@ -23,14 +23,6 @@ impl<'f> TryFrom<&'_ LitchiMission> for FlightPlan<'f> {
             })
             .collect();
 
-        if let Some(last) = waypoints.last_mut() {
-            if let Some(actions) = last.actions.as_mut() {
-                actions.push(Action::VideoStopCapture);
-            } else {
-                last.actions = Some(vec![Action::VideoStopCapture]);
-            }
-        }
-
         let start = waypoints
             .first()
             .ok_or(Self::Error::MalformedLitchiMission("missing start point"))?;
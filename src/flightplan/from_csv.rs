@@ -1,5 +1,3 @@
-use std::collections::HashSet;
-
 use crate::{
     error::Error,
     litchi::csv::de::{Coordinates, MissionRecord},
@@ -9,84 +7,78 @@ use super::{Action, FlightPlan, PointOfInterest, Waypoint};
 
 use crate::litchi::csv::de::Altitude;
 
-type PoiKey = (u64, u64);
-
-impl<'a, 'f> TryFrom<&'a [MissionRecord]> for FlightPlan<'f> {
-    type Error = Error;
-
-    fn try_from(records: &'a [MissionRecord]) -> Result<Self, Self::Error> {
-        let poi: HashSet<_> = records
-            .iter()
-            .filter_map(Option::<PointOfInterest>::from)
-            .collect();
-
-        let color_roll = super::POI_COLORS.iter().cycle();
-
-        let mut poi: Vec<_> = poi
-            .into_iter()
-            .scan(color_roll, |picker, mut poi| {
-                if let Some(color) = picker.next() {
-                    poi.color = *color;
+/// FreeFlight references points of interest by an `u8` index.
+pub(super) const MAX_POI: usize = u8::MAX as usize + 1;
+
+/// Converts records to a flight plan. Points of interest closer than `poi_tolerance` meters
+/// are merged, and kept in the order they first appear in.
+pub(super) fn flightplan<'f>(
+    records: &[MissionRecord],
+    poi_tolerance: f64,
+) -> Result<FlightPlan<'f>, Error> {
+    let mut poi: Vec<PointOfInterest> = vec![];
+
+    let mut waypoints: Vec<Waypoint> = Vec::with_capacity(records.len());
+
+    for r in records {
+        let mut wp = Waypoint::from(r);
+
+        if let Some(candidate) = Option::<PointOfInterest>::from(r) {
+            let index = match poi
+                .iter()
+                .position(|p| p.is_near(&candidate, poi_tolerance))
+            {
+                Some(index) => index,
+                None => {
+                    poi.push(candidate);
+                    poi.len() - 1
                 }
+            };
 
-                Some(poi)
-            })
-            .collect();
-
-        let mut waypoints: Vec<Waypoint> = Vec::with_capacity(records.len());
-
-        for r in records {
-            let mut wp = Waypoint::from(r);
-
-            if let Some::<PoiKey>(key_b) = Option::from(r) {
-                for (i, p) in poi.iter().enumerate() {
-                    let key_a = PoiKey::from(p);
-
-                    if key_a.eq(&key_b) {
-                        wp.poi = Some(i as u8);
-                        wp.follow_poi = true;
-                    }
-                }
+            if poi.len() > MAX_POI {
+                return Err(Error::TooManyPointsOfInterest(poi.len()));
             }
-            waypoints.push(wp);
-        }
 
-        if let Some(last) = waypoints.last_mut() {
-            last.actions = Some(vec![Action::VideoStopCapture]);
+            wp.poi = Some(index as u8);
+            wp.follow_poi = true;
         }
 
-        let start = waypoints
-            .first()
-            .ok_or(Error::MalformedLitchiMission("missing start point"))?;
+        waypoints.push(wp);
+    }
 
-        let latitude = start.latitude;
-        let longitude = start.longitude;
+    for (p, color) in poi.iter_mut().zip(super::POI_COLORS.iter().cycle()) {
+        p.color = *color;
+    }
 
-        let mut flightplan = FlightPlan::new("", latitude, longitude);
+    let start = waypoints
+        .first()
+        .ok_or(Error::MalformedLitchiMission("missing start point"))?;
 
-        flightplan.plan.poi.append(&mut poi);
+    let latitude = start.latitude;
+    let longitude = start.longitude;
 
-        flightplan.plan.waypoints.append(&mut waypoints);
+    let mut flightplan = FlightPlan::new("", latitude, longitude);
 
-        Ok(flightplan)
-    }
-}
+    flightplan.plan.poi.append(&mut poi);
 
-impl<'a> From<&'a MissionRecord> for Option<PoiKey> {
-    fn from(record: &'a MissionRecord) -> Self {
-        record.poi.as_ref().map(
-            |Coordinates {
-                 latitude,
-                 longitude,
-                 ..
-             }| (latitude.to_bits(), longitude.to_bits()),
-        )
-    }
+    flightplan.plan.waypoints.append(&mut waypoints);
+
+    Ok(flightplan)
 }
 
-impl<'a> From<&'a PointOfInterest> for PoiKey {
-    fn from(poi: &'a PointOfInterest) -> Self {
-        (poi.latitude.to_bits(), poi.longitude.to_bits())
+impl PointOfInterest {
+    pub(super) fn is_near(&self, other: &PointOfInterest, tolerance: f64) -> bool {
+        let distance = super::distance(
+            self.latitude,
+            self.longitude,
+            other.latitude,
+            other.longitude,
+        );
+
+        // Extreme altitudes are further apart than i16 can hold.
+        let height = (self.altitude as f64 - other.altitude as f64).abs();
+
+        distance <= tolerance && height <= tolerance
     }
 }
 
@@ -113,8 +105,6 @@ impl<'a> From<&'a MissionRecord> for Option<PointOfInterest> {
 
 impl<'a> From<&'a MissionRecord> for Waypoint {
     fn from(rec: &'a MissionRecord) -> Self {
-        use Altitude::*;
-
         let actions: Vec<Action> = rec.actions.iter().map(Action::from).collect();
 
         let actions = if actions.is_empty() {
@@ -132,7 +122,7 @@ impl<'a> From<&'a MissionRecord> for Waypoint {
             latitude: rec.waypoint.latitude,
             longitude: rec.waypoint.longitude,
             altitude: match rec.waypoint.altitude {
-                AboveGround(x) | Absolute(x) => x,
+                Altitude::AboveGround(x) | Altitude::Absolute(x) => x,
             },
             yaw: 360.0 - rec.heading,
             speed,
@@ -150,10 +140,51 @@ mod tests {
 
     #[test]
     fn lol() {
-        let a = std::f64::INFINITY;
+        let a = f64::INFINITY;
 
         let b = a as u8;
 
         println!("{}, {}", a, b);
     }
+
+    #[test]
+    fn poi_are_merged_in_order() {
+        let csv = "\
+latitude,longitude,altitude(m),heading(deg),curvesize(m),rotationdir,gimbalmode,gimbalpitchangle,altitudemode,speed(m/s),poi_latitude,poi_longitude,poi_altitude(m),poi_altitudemode,photo_timeinterval,photo_distinterval
+48.8566,2.3522,30,0,0,0,0,0,1,5,48.8600,2.3600,0,0,-1,-1
+48.8576,2.3532,30,0,0,0,0,0,1,5,48.8500,2.3500,0,0,-1,-1
+48.8586,2.3542,30,0,0,0,0,0,1,5,48.860001,2.360001,0,0,-1,-1
+";
+
+        let records: Vec<crate::litchi::csv::de::MissionRecord> =
+            csv::Reader::from_reader(csv.as_bytes())
+                .deserialize()
+                .collect::<Result<_, _>>()
+                .unwrap();
+
+        let flightplan = super::flightplan(&records, 1.0).unwrap();
+
+        let poi: Vec<_> = flightplan.plan.poi.iter().map(|p| p.latitude).collect();
+        let indexes: Vec<_> = flightplan.plan.waypoints.iter().map(|w| w.poi).collect();
+
+        assert_eq!(poi, [48.86, 48.85]);
+        assert_eq!(indexes, [Some(0), Some(1), Some(0)]);
+        assert_eq!(
+            flightplan.plan.poi[0].color,
+            crate::flightplan::POI_COLORS[0]
+        );
+    }
+
+    #[test]
+    fn far_apart_altitudes_are_not_near() {
+        let poi = |altitude| crate::flightplan::PointOfInterest {
+            latitude: 48.8583,
+            longitude: 2.2945,
+            altitude,
+            color: 0,
+        };
+
+        assert!(!poi(i16::MAX).is_near(&poi(i16::MIN), 1.0));
+        assert!(poi(10).is_near(&poi(11), 1.0));
+    }
 }
@@ -0,0 +1,288 @@
+use serde::Deserialize;
+
+use crate::error::Error;
+
+use super::{
+    color::POI_COLORS, from_csv::MAX_POI, from_kml::altitude_checked, Action, FlightPlan,
+    PointOfInterest, Waypoint,
+};
+
+/// The parts of a GeoJSON `FeatureCollection` making a flight plan,
+/// as written by [`FlightPlan::to_geojson`] or drawn in a GIS.
+#[derive(Debug, Deserialize)]
+pub struct FeatureCollection {
+    #[serde(default)]
+    pub name: Option<String>,
+
+    #[serde(default)]
+    properties: Option<CollectionProperties>,
+
+    features: Vec<Feature>,
+}
+
+/// Foreign members of the collection, as written by [`FlightPlan::to_geojson`].
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct CollectionProperties {
+    takeoff: Option<Vec<Action>>,
+}
+
+impl FeatureCollection {
+    /// Actions after takeoff, when the collection carries them.
+    pub(super) fn takeoff(&self) -> Option<Vec<Action>> {
+        self.properties.as_ref()?.takeoff.clone()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Feature {
+    geometry: Option<Geometry>,
+
+    #[serde(default)]
+    properties: Option<Properties>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type")]
+enum Geometry {
+    Point {
+        coordinates: Vec<f64>,
+    },
+    LineString {
+        coordinates: Vec<Vec<f64>>,
+    },
+
+    #[serde(other)]
+    Other,
+}
+
+/// GIS tools may write any number as a float, so they all are.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Properties {
+    kind: Option<String>,
+    number: Option<f64>,
+    altitude: Option<f64>,
+    yaw: Option<f64>,
+    speed: Option<f64>,
+    poi: Option<f64>,
+
+    #[serde(rename = "continue")]
+    dont_stop: Option<bool>,
+
+    actions: Option<Vec<Action>>,
+    color: Option<f64>,
+}
+
+/// `[longitude, latitude, altitude]`, altitude being optional.
+fn position(coordinates: &[f64]) -> Result<(f64, f64, Option<f64>), Error> {
+    match *coordinates {
+        [longitude, latitude] => Ok((latitude, longitude, None)),
+        [longitude, latitude, altitude, ..] => Ok((latitude, longitude, Some(altitude))),
+
+        _ => Err(Error::MalformedGeoJson("a position needs two coordinates")),
+    }
+}
+
+/// Waypoints are the `Point` features of kind `waypoint`, or without kind, ordered by number.
+/// When there are none, the vertices of the first `LineString` are used instead.
+impl<'f> TryFrom<&'_ FeatureCollection> for FlightPlan<'f> {
+    type Error = Error;
+
+    fn try_from(collection: &FeatureCollection) -> Result<Self, Self::Error> {
+        let mut waypoints = vec![];
+        let mut pois = vec![];
+        let mut path = None;
+
+        let no_properties = Properties::default();
+
+        for feature in &collection.features {
+            let properties = feature.properties.as_ref().unwrap_or(&no_properties);
+
+            match (&feature.geometry, properties.kind.as_deref()) {
+                (Some(Geometry::Point { coordinates }), Some("poi")) => {
+                    pois.push((properties, position(coordinates)?))
+                }
+
+                (Some(Geometry::Point { coordinates }), Some("waypoint") | None) => {
+                    waypoints.push((properties, position(coordinates)?))
+                }
+
+                (Some(Geometry::LineString { coordinates }), _) if path.is_none() => {
+                    path = Some(coordinates)
+                }
+
+                _ => (),
+            }
+        }
+
+        if pois.len() > MAX_POI {
+            return Err(Error::TooManyPointsOfInterest(pois.len()));
+        }
+
+        // Stable sorts: unnumbered features stay in order, after numbered ones.
+        let by_number = |(p, _): &(&Properties, _)| p.number.unwrap_or(f64::INFINITY);
+
+        pois.sort_by(|a, b| by_number(a).total_cmp(&by_number(b)));
+        waypoints.sort_by(|a, b| by_number(a).total_cmp(&by_number(b)));
+
+        if waypoints.is_empty() {
+            for coordinates in path.into_iter().flatten() {
+                waypoints.push((&no_properties, position(coordinates)?));
+            }
+        }
+
+        let poi = pois
+            .iter()
+            .enumerate()
+            .map(
+                |(index, (properties, (latitude, longitude, altitude)))| PointOfInterest {
+                    latitude: *latitude,
+                    longitude: *longitude,
+                    altitude: properties.altitude.or(*altitude).unwrap_or_default() as i16,
+                    color: properties
+                        .color
+                        .map(|color| color as i32)
+                        .unwrap_or(POI_COLORS[index % POI_COLORS.len()]),
+                },
+            )
+            .collect::<Vec<_>>();
+
+        let waypoints = waypoints
+            .into_iter()
+            .map(|(properties, (latitude, longitude, altitude))| {
+                // Numbered from 1, unknown points of interest are ignored.
+                let poi = properties
+                    .poi
+                    .map(|number| number as usize)
+                    .filter(|number| (1..=poi.len()).contains(number))
+                    .map(|number| (number - 1) as u8);
+
+                Ok(Waypoint {
+                    latitude,
+                    longitude,
+                    altitude: altitude_checked(properties.altitude.or(altitude))?,
+                    yaw: properties.yaw.unwrap_or_default(),
+                    speed: properties
+                        .speed
+                        .map(|speed| speed as u8)
+                        .unwrap_or(super::DEFAULT_SPEED_MS),
+                    poi,
+                    dont_stop: properties.dont_stop.unwrap_or(true),
+                    follow_poi: poi.is_some(),
+                    follow: 1,
+                    last_yaw: 0f64,
+                    actions: properties.actions.clone(),
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        let start = waypoints
+            .first()
+            .ok_or(Error::MalformedGeoJson("no waypoint"))?;
+
+        let mut flightplan = FlightPlan::new(
+            collection.name.as_deref().unwrap_or_default(),
+            start.latitude,
+            start.longitude,
+        );
+
+        flightplan.plan.poi = poi;
+        flightplan.plan.waypoints = waypoints;
+
+        Ok(flightplan)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::flightplan::{
+        self, Action, FlightPlan, PointOfInterest, Settings, Takeoff, TakeoffAction, Waypoint,
+    };
+
+    use super::FeatureCollection;
+
+    fn parse(geojson: &str, title: &str, settings: &Settings) -> FlightPlan<'static> {
+        let collection: FeatureCollection = serde_json::from_str(geojson).unwrap();
+
+        flightplan::from_geojson(title, &collection, geojson.as_bytes(), settings).unwrap()
+    }
+
+    #[test]
+    fn plans_survive_a_round_trip() {
+        let mut flightplan = FlightPlan::new("bridge", 48.8583, 2.2945);
+
+        flightplan.plan.poi = vec![PointOfInterest {
+            latitude: 48.8584,
+            longitude: 2.2946,
+            altitude: 2,
+            color: -7589836,
+        }];
+
+        flightplan.plan.waypoints = [(30, None), (45, Some(0))]
+            .into_iter()
+            .map(|(altitude, poi)| {
+                Waypoint::at(48.8583, 2.2945)
+                    .with_altitude(altitude)
+                    .with_yaw(90.0)
+                    .with_speed(8)
+                    .with_poi(poi)
+                    .with_actions(vec![Action::Delay { delay: 3 }])
+            })
+            .collect();
+
+        flightplan.apply(&Settings {
+            takeoff: Some(Takeoff(vec![TakeoffAction::Tilt { angle: -30 }])),
+            ..Default::default()
+        });
+
+        let geojson = serde_json::to_string(&flightplan.to_geojson()).unwrap();
+
+        let mut parsed = parse(&geojson, "unused", &Settings::default());
+
+        parsed.uuid = flightplan.uuid.clone();
+
+        assert!(parsed.same_content(&flightplan));
+
+        // Chosen takeoff actions still win over the carried ones.
+        let settings = Settings {
+            takeoff: Some(Takeoff(vec![])),
+            ..Default::default()
+        };
+
+        assert!(parse(&geojson, "unused", &settings).plan.takeoff.is_empty());
+    }
+
+    #[test]
+    fn a_bare_line_makes_waypoints() {
+        let flightplan = parse(
+            r#"{
+                "type": "FeatureCollection",
+                "features": [{
+                    "type": "Feature",
+                    "properties": null,
+                    "geometry": {
+                        "type": "LineString",
+                        "coordinates": [[2.2945, 48.8583, 20], [2.2955, 48.8593]]
+                    }
+                }]
+            }"#,
+            "line",
+            &Settings::default(),
+        );
+
+        let altitudes: Vec<_> = flightplan
+            .plan
+            .waypoints
+            .iter()
+            .map(|waypoint| waypoint.altitude)
+            .collect();
+
+        assert_eq!(flightplan.title, "line");
+        assert_eq!(altitudes, [20, super::super::DEFAULT_WAYPOINT_ALTITUDE_M]);
+        assert!(matches!(
+            flightplan.plan.takeoff.as_slice(),
+            [Action::VideoStartCapture { .. }]
+        ));
+    }
+}
@@ -0,0 +1,407 @@
+use std::collections::HashMap;
+
+use quick_xml::{events::Event, Reader};
+
+use crate::error::Error;
+
+use super::{
+    color::POI_COLORS, from_csv::MAX_POI, from_kml::altitude_checked, Action, FlightPlan,
+    PointOfInterest, Waypoint,
+};
+
+/// `type` of the GPX waypoints standing for points of interest.
+pub(super) const POI_TYPE: &str = "poi";
+
+/// The parts of a GPX file making a flight plan: the points of the first route,
+/// or of the first track when there is no route, and the waypoints of type `poi`.
+#[derive(Debug, Default)]
+pub struct Gpx {
+    pub name: Option<String>,
+
+    /// Elevation of the takeoff point above sea level, elevations are relative to.
+    /// The first elevation of the path when not given.
+    pub takeoff_elevation: Option<f64>,
+
+    points: Vec<Point>,
+    pois: Vec<Point>,
+}
+
+#[derive(Debug, Default)]
+struct Point {
+    latitude: f64,
+    longitude: f64,
+
+    /// Above sea level
+    elevation: Option<f64>,
+
+    kind: Option<String>,
+
+    /// Extension elements by local name, whatever their namespace
+    extensions: HashMap<String, String>,
+}
+
+#[derive(Default)]
+struct Path {
+    name: Option<String>,
+    points: Vec<Point>,
+}
+
+impl Gpx {
+    pub fn from_slice(data: &[u8]) -> Result<Self, Error> {
+        let mut reader = Reader::from_reader(data);
+
+        reader.trim_text(true).expand_empty_elements(true);
+
+        let mut buf = vec![];
+
+        // Local names of the open elements
+        let mut open: Vec<String> = vec![];
+
+        let mut metadata_name = None;
+        let mut routes: Vec<Path> = vec![];
+        let mut tracks: Vec<Path> = vec![];
+        let mut waypoints = vec![];
+
+        let mut point: Option<Point> = None;
+
+        loop {
+            match reader.read_event(&mut buf)? {
+                Event::Start(e) => {
+                    let name = reader.decode(e.local_name())?.to_owned();
+
+                    match name.as_str() {
+                        "rte" => routes.push(Path::default()),
+                        "trk" => tracks.push(Path::default()),
+
+                        "rtept" | "trkpt" | "wpt" => {
+                            let mut latitude = None;
+                            let mut longitude = None;
+
+                            for attribute in e.attributes() {
+                                let attribute = attribute?;
+
+                                let value = attribute.unescape_and_decode_value(&reader)?;
+
+                                match attribute.key {
+                                    b"lat" => latitude = value.trim().parse().ok(),
+                                    b"lon" => longitude = value.trim().parse().ok(),
+                                    _ => (),
+                                }
+                            }
+
+                            match (latitude, longitude) {
+                                (Some(latitude), Some(longitude)) => {
+                                    point = Some(Point {
+                                        latitude,
+                                        longitude,
+                                        ..Default::default()
+                                    })
+                                }
+
+                                _ => return Err(Error::MalformedGpx("a point has no lat or lon")),
+                            }
+                        }
+
+                        _ => (),
+                    }
+
+                    open.push(name);
+                }
+
+                Event::Text(e) => {
+                    let text = e.unescape_and_decode(&reader)?;
+
+                    let parent = open.iter().rev().nth(1).map(String::as_str);
+
+                    match (open.last().map(String::as_str), parent, point.as_mut()) {
+                        (Some("ele"), _, Some(point)) => point.elevation = text.trim().parse().ok(),
+
+                        (Some("type"), _, Some(point)) => point.kind = Some(text),
+
+                        (Some(name), Some("extensions"), Some(point)) => {
+                            point.extensions.insert(name.to_owned(), text);
+                        }
+
+                        (Some("name"), Some("metadata"), _) => metadata_name = Some(text),
+                        (Some("name"), Some("rte"), _) => {
+                            if let Some(route) = routes.last_mut() {
+                                route.name = Some(text)
+                            }
+                        }
+
+                        (Some("name"), Some("trk"), _) => {
+                            if let Some(track) = tracks.last_mut() {
+                                track.name = Some(text)
+                            }
+                        }
+
+                        _ => (),
+                    }
+                }
+
+                Event::End(_) => {
+                    let path = match open.pop().as_deref() {
+                        Some("rtept") => routes.last_mut(),
+                        Some("trkpt") => tracks.last_mut(),
+
+                        Some("wpt") => {
+                            waypoints.extend(point.take());
+
+                            None
+                        }
+
+                        _ => None,
+                    };
+
+                    if let Some(path) = path {
+                        path.points.extend(point.take());
+                    }
+                }
+
+                Event::Eof => break,
+
+                _ => (),
+            }
+
+            buf.clear();
+        }
+
+        let path = routes
+            .into_iter()
+            .find(|route| !route.points.is_empty())
+            .or_else(|| tracks.into_iter().find(|track| !track.points.is_empty()))
+            .unwrap_or_default();
+
+        Ok(Gpx {
+            name: path.name.or(metadata_name),
+            takeoff_elevation: None,
+            points: path.points,
+            pois: waypoints
+                .into_iter()
+                .filter(|point| point.kind.as_deref() == Some(POI_TYPE))
+                .collect(),
+        })
+    }
+}
+
+impl Gpx {
+    fn takeoff_elevation(&self) -> Option<f64> {
+        self.takeoff_elevation
+            .or_else(|| self.points.iter().find_map(|point| point.elevation))
+    }
+}
+
+impl Point {
+    /// Altitude relative to takeoff: the `altitude` extension, as written by litchi2fp,
+    /// or the elevation above `takeoff_elevation`.
+    /// Points at or under the takeoff point are flown 1 m above it.
+    fn altitude(&self, takeoff_elevation: Option<f64>) -> Result<u16, Error> {
+        let height = self.height(takeoff_elevation).map(|height| height.max(1.0));
+
+        altitude_checked(self.extension("altitude").or(height))
+    }
+
+    /// Elevation above the takeoff point.
+    fn height(&self, takeoff_elevation: Option<f64>) -> Option<f64> {
+        self.elevation
+            .zip(takeoff_elevation)
+            .map(|(elevation, takeoff)| elevation - takeoff)
+    }
+
+    fn extension(&self, name: &str) -> Option<f64> {
+        self.extensions.get(name)?.trim().parse().ok()
+    }
+}
+
+impl<'f> TryFrom<&'_ Gpx> for FlightPlan<'f> {
+    type Error = Error;
+
+    fn try_from(gpx: &Gpx) -> Result<Self, Self::Error> {
+        if gpx.pois.len() > MAX_POI {
+            return Err(Error::TooManyPointsOfInterest(gpx.pois.len()));
+        }
+
+        let takeoff_elevation = gpx.takeoff_elevation();
+
+        let poi = gpx
+            .pois
+            .iter()
+            .enumerate()
+            .map(|(index, point)| PointOfInterest {
+                latitude: point.latitude,
+                longitude: point.longitude,
+                altitude: point
+                    .extension("altitude")
+                    .or_else(|| point.height(takeoff_elevation))
+                    .unwrap_or_default() as i16,
+                color: point
+                    .extension("color")
+                    .map(|color| color as i32)
+                    .unwrap_or(POI_COLORS[index % POI_COLORS.len()]),
+            })
+            .collect::<Vec<_>>();
+
+        let waypoints = gpx
+            .points
+            .iter()
+            .map(|point| {
+                // Numbered from 1, unknown points of interest are ignored.
+                let poi = point
+                    .extension("poi")
+                    .map(|number| number as usize)
+                    .filter(|number| (1..=poi.len()).contains(number))
+                    .map(|number| (number - 1) as u8);
+
+                let actions = match point.extensions.get("actions") {
+                    Some(actions) => Some(
+                        serde_json::from_str::<Vec<Action>>(actions)
+                            .map_err(|_| Error::MalformedGpx("unreadable actions"))?,
+                    ),
+
+                    None => None,
+                };
+
+                Ok(Waypoint {
+                    latitude: point.latitude,
+                    longitude: point.longitude,
+                    altitude: point.altitude(takeoff_elevation)?,
+                    yaw: point.extension("yaw").unwrap_or_default(),
+                    speed: point
+                        .extension("speed")
+                        .map(|speed| speed as u8)
+                        .unwrap_or(super::DEFAULT_SPEED_MS),
+                    poi,
+                    dont_stop: true,
+                    follow_poi: poi.is_some(),
+                    follow: 1,
+                    last_yaw: 0f64,
+                    actions,
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        let start = waypoints
+            .first()
+            .ok_or(Error::MalformedGpx("no route or track point"))?;
+
+        let mut flightplan = FlightPlan::new(
+            gpx.name.as_deref().unwrap_or_default(),
+            start.latitude,
+            start.longitude,
+        );
+
+        flightplan.plan.poi = poi;
+        flightplan.plan.waypoints = waypoints;
+
+        Ok(flightplan)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::flightplan::{Action, FlightPlan, PointOfInterest, Waypoint};
+
+    use super::Gpx;
+
+    #[test]
+    fn plans_survive_a_round_trip() {
+        let mut flightplan = FlightPlan::new("pylon <north>", 48.8583, 2.2945);
+
+        flightplan.plan.poi = vec![PointOfInterest {
+            latitude: 48.8584,
+            longitude: 2.2946,
+            altitude: 2,
+            color: -7589836,
+        }];
+
+        flightplan.plan.waypoints = [(30, None), (45, Some(0))]
+            .into_iter()
+            .map(|(altitude, poi)| {
+                Waypoint::at(48.8583, 2.2945)
+                    .with_altitude(altitude)
+                    .with_yaw(90.0)
+                    .with_speed(8)
+                    .with_poi(poi)
+                    .with_actions(vec![Action::Delay { delay: 3 }])
+            })
+            .collect();
+
+        let mut gpx = vec![];
+
+        flightplan.to_gpx(Some(120.0), &mut gpx).unwrap();
+
+        let mut parsed = FlightPlan::try_from(&Gpx::from_slice(&gpx).unwrap()).unwrap();
+
+        parsed.uuid = flightplan.uuid.clone();
+
+        assert!(parsed.same_content(&flightplan));
+    }
+
+    #[test]
+    fn tracks_are_read_when_there_is_no_route() {
+        let gpx = br#"<?xml version="1.0"?>
+            <gpx version="1.1" xmlns="http://www.topografix.com/GPX/1/1">
+              <metadata><name>Ridge</name></metadata>
+              <trk>
+                <trkseg>
+                  <trkpt lat="45.1" lon="6.1"><ele>52</ele></trkpt>
+                  <trkpt lat="45.2" lon="6.2"><ele>82.4</ele></trkpt>
+                  <trkpt lat="45.3" lon="6.3"/>
+                </trkseg>
+              </trk>
+            </gpx>"#;
+
+        let gpx = Gpx::from_slice(gpx).unwrap();
+
+        let flightplan = FlightPlan::try_from(&gpx).unwrap();
+
+        let altitudes: Vec<_> = flightplan
+            .plan
+            .waypoints
+            .iter()
+            .map(|waypoint| waypoint.altitude)
+            .collect();
+
+        assert_eq!(flightplan.title, "Ridge");
+        // Relative to the first elevation, the takeoff point's
+        assert_eq!(
+            altitudes,
+            [1, 30, super::super::DEFAULT_WAYPOINT_ALTITUDE_M]
+        );
+    }
+
+    #[test]
+    fn elevations_are_relative_to_takeoff() {
+        let mut flightplan = FlightPlan::new("ridge", 45.0, 6.0);
+
+        flightplan.plan.waypoints = vec![
+            Waypoint::at(45.0, 6.0).with_altitude(20),
+            Waypoint::at(45.001, 6.0).with_altitude(45),
+        ];
+
+        let mut gpx = vec![];
+
+        flightplan.to_gpx(Some(1210.0), &mut gpx).unwrap();
+
+        // Only elevations, as written by other tools
+        let gpx = String::from_utf8(gpx).unwrap();
+        let gpx: String = gpx
+            .lines()
+            .filter(|line| !line.contains("ff:altitude"))
+            .collect();
+
+        let mut parsed = Gpx::from_slice(gpx.as_bytes()).unwrap();
+
+        parsed.takeoff_elevation = Some(1210.0);
+
+        let altitudes: Vec<_> = FlightPlan::try_from(&parsed)
+            .unwrap()
+            .plan
+            .waypoints
+            .iter()
+            .map(|waypoint| waypoint.altitude)
+            .collect();
+
+        assert_eq!(altitudes, [20, 45]);
+    }
+}
@@ -7,7 +7,6 @@ type Result<T> = std::result::Result<T, Error>;
 use crate::{flightplan::model::Waypoint, litchi::kml::Mission};
 
 pub use super::model::FlightPlan;
-use super::Action;
 
 impl<'m, 'f> TryFrom<&'_ Mission<'m>> for FlightPlan<'f> {
     type Error = crate::error::Error;
@@ -20,10 +19,6 @@ impl<'m, 'f> TryFrom<&'_ Mission<'m>> for FlightPlan<'f> {
 
         let mut waypoints = waypoints?;
 
-        if let Some(last) = waypoints.last_mut() {
-            last.actions = Some(vec![Action::VideoStopCapture]);
-        }
-
         let start = waypoints
             .first()
             .ok_or(Error::MalformedLitchiMission("missing start point"))?;
@@ -80,7 +75,7 @@ impl<'a> TryFrom<&'a Coord> for Waypoint {
     }
 }
 
-fn altitude_checked(altitude: Option<f64>) -> Result<u16> {
+pub(super) fn altitude_checked(altitude: Option<f64>) -> Result<u16> {
     use std::num::IntErrorKind::*;
     use Error::AltitudeOverflow;
 
@@ -0,0 +1,419 @@
+use std::fmt::Display;
+
+use crate::{
+    error::Error,
+    mavlink::{self, command::*, frame, MissionItem},
+};
+
+use super::{
+    from_csv::MAX_POI, from_kml::altitude_checked, geo::bearing, Action, CameraSettings,
+    FlightPlan, PointOfInterest, TakeoffAction, Waypoint, POI_COLORS,
+};
+
+/// An item that couldn't be converted as is.
+#[derive(Debug)]
+pub struct Loss {
+    /// Position of the item in the mission
+    pub seq: usize,
+
+    pub command: u16,
+
+    /// What was done instead
+    pub reason: String,
+}
+
+impl Display for Loss {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match mavlink::command::name(self.command) {
+            Some(name) => write!(f, "item {} ({}): {}", self.seq, name, self.reason),
+            None => write!(
+                f,
+                "item {} (command {}): {}",
+                self.seq, self.command, self.reason
+            ),
+        }
+    }
+}
+
+/// Converts MAVLink items, as flown by ArduPilot, to a flight plan.
+/// When the first item is a waypoint above sea level, it is the home position
+/// the other absolute altitudes are relative to, as in Mission Planner files.
+/// Points of interest closer than `poi_tolerance` meters are merged.
+pub(super) fn flightplan<'f>(
+    items: &[MissionItem],
+    poi_tolerance: f64,
+) -> Result<(FlightPlan<'f>, Vec<Loss>), Error> {
+    let home = items
+        .first()
+        .filter(|item| item.command == NAV_WAYPOINT && item.frame == frame::GLOBAL);
+
+    let mut mission = Mission {
+        home_altitude: home.map(|home| home.z),
+        poi_tolerance,
+        speed: super::DEFAULT_SPEED_MS,
+        poi: None,
+        pois: vec![],
+        waypoints: vec![],
+        headings: vec![],
+        pending: vec![],
+        losses: vec![],
+    };
+
+    let skipped = usize::from(home.is_some());
+
+    for (seq, item) in items.iter().enumerate().skip(skipped) {
+        mission.add(seq, item)?;
+    }
+
+    let Mission {
+        mut waypoints,
+        headings,
+        pois,
+        losses,
+        ..
+    } = mission;
+
+    // ArduPilot heads to the next waypoint unless told otherwise.
+    for index in 0..waypoints.len() {
+        let heading = headings[index].unwrap_or_else(|| {
+            let (from, to) = match (waypoints.get(index), waypoints.get(index + 1)) {
+                (Some(from), Some(to)) => (from, to),
+                _ if index > 0 => (&waypoints[index - 1], &waypoints[index]),
+                _ => return 0.0,
+            };
+
+            bearing(from.latitude, from.longitude, to.latitude, to.longitude)
+        });
+
+        // FreeFlight's yaw grows counterclockwise, headings clockwise.
+        waypoints[index].yaw = (360.0 - heading).rem_euclid(360.0);
+    }
+
+    let start = waypoints.first().ok_or(Error::NoWaypoint)?;
+
+    let mut flightplan = FlightPlan::new("", start.latitude, start.longitude);
+
+    flightplan.plan.poi = pois;
+    flightplan.plan.waypoints = waypoints;
+
+    Ok((flightplan, losses))
+}
+
+struct Mission {
+    home_altitude: Option<f64>,
+    poi_tolerance: f64,
+
+    /// Speed and point of interest of the next waypoints
+    speed: u8,
+    poi: Option<u8>,
+
+    pois: Vec<PointOfInterest>,
+    waypoints: Vec<Waypoint>,
+
+    /// Heading set at each waypoint, if any
+    headings: Vec<Option<f64>>,
+
+    /// Actions met before the first waypoint
+    pending: Vec<Action>,
+
+    losses: Vec<Loss>,
+}
+
+impl Mission {
+    fn add(&mut self, seq: usize, item: &MissionItem) -> Result<(), Error> {
+        let [p1, p2, _, p4] = item.params;
+
+        match item.command {
+            NAV_WAYPOINT | NAV_SPLINE_WAYPOINT | NAV_LOITER_TIME | NAV_LOITER_TURNS
+            | NAV_LOITER_UNLIM
+                if item.x == 0.0 && item.y == 0.0 =>
+            {
+                self.lose(seq, item, "no position, ignored")
+            }
+
+            NAV_WAYPOINT | NAV_SPLINE_WAYPOINT | NAV_LOITER_TIME | NAV_LOITER_TURNS
+            | NAV_LOITER_UNLIM => {
+                match item.command {
+                    NAV_SPLINE_WAYPOINT => self.lose(seq, item, "flown in a straight line"),
+                    NAV_LOITER_TURNS => {
+                        self.lose(seq, item, format!("{} turns replaced by a stop", p1))
+                    }
+                    NAV_LOITER_UNLIM => {
+                        self.lose(seq, item, "loitering forever replaced by a stop")
+                    }
+                    _ => (),
+                }
+
+                let altitude = self.altitude(seq, item);
+
+                let mut waypoint = Waypoint {
+                    latitude: item.x,
+                    longitude: item.y,
+                    altitude: altitude_checked(Some(altitude.round()))?,
+                    yaw: 0.0,
+                    speed: self.speed,
+                    poi: self.poi,
+                    dont_stop: true,
+                    follow_poi: self.poi.is_some(),
+                    follow: 1,
+                    last_yaw: 0f64,
+                    actions: None,
+                };
+
+                if !self.pending.is_empty() {
+                    waypoint.actions = Some(self.pending.split_off(0));
+                }
+
+                self.waypoints.push(waypoint);
+                self.headings.push(None);
+
+                // Loitering is holding, in seconds for both.
+                let hold = match item.command {
+                    NAV_WAYPOINT | NAV_LOITER_TIME => p1,
+                    _ => 0.0,
+                };
+
+                if hold >= 1.0 {
+                    self.act(Action::Delay {
+                        delay: hold.round() as usize,
+                    });
+                }
+            }
+
+            // FreeFlight takes off by itself.
+            NAV_TAKEOFF => (),
+
+            NAV_LAND => self.act(Action::Landing),
+
+            NAV_RETURN_TO_LAUNCH => self.lose(
+                seq,
+                item,
+                "not supported, the plan ends at the last waypoint",
+            ),
+
+            NAV_DELAY if p1 >= 0.0 => self.act(Action::Delay {
+                delay: p1.round() as usize,
+            }),
+
+            DO_CHANGE_SPEED if p2 > 0.0 => self.speed = p2.round().clamp(1.0, u8::MAX as f64) as u8,
+
+            // Absolute heading, at the last waypoint
+            CONDITION_YAW if p4 == 0.0 => match self.headings.last_mut() {
+                Some(heading) => *heading = Some(p1.rem_euclid(360.0)),
+                None => self.lose(seq, item, "before the first waypoint, ignored"),
+            },
+
+            // Relative turn, clockwise when the direction is 1.
+            CONDITION_YAW => {
+                let angle = if item.params[2] < 0.0 { p1 } else { -p1 };
+
+                let clamped = angle.round().clamp(i8::MIN as f64, i8::MAX as f64);
+
+                if clamped != angle.round() {
+                    self.lose(
+                        seq,
+                        item,
+                        format!("turn of {}° limited to {}°", angle, clamped),
+                    );
+                }
+
+                self.act(Action::Panorama {
+                    angle: clamped as i8,
+                    speed: 10,
+                })
+            }
+
+            DO_SET_ROI_NONE => self.poi = None,
+
+            // Mission Planner leaves the mode at 0, which ArduPilot ignores:
+            // only a location at 0, 0 stops looking at a point of interest.
+            DO_SET_ROI if item.x == 0.0 && item.y == 0.0 => self.poi = None,
+
+            DO_SET_ROI if p1 != mavlink::ROI_NONE && p1 != mavlink::ROI_LOCATION => {
+                self.lose(seq, item, "only locations can be looked at, ignored")
+            }
+
+            DO_SET_ROI | DO_SET_ROI_LOCATION => {
+                let candidate = PointOfInterest {
+                    latitude: item.x,
+                    longitude: item.y,
+                    altitude: self.altitude(seq, item).round() as i16,
+                    color: POI_COLORS[self.pois.len() % POI_COLORS.len()],
+                };
+
+                let index = match self
+                    .pois
+                    .iter()
+                    .position(|poi| poi.is_near(&candidate, self.poi_tolerance))
+                {
+                    Some(index) => index,
+
+                    None => {
+                        self.pois.push(candidate);
+                        self.pois.len() - 1
+                    }
+                };
+
+                if self.pois.len() > MAX_POI {
+                    return Err(Error::TooManyPointsOfInterest(self.pois.len()));
+                }
+
+                self.poi = Some(index as u8);
+            }
+
+            DO_MOUNT_CONTROL => self.act(
+                TakeoffAction::Tilt {
+                    angle: p1.round().clamp(-90.0, 90.0) as i8,
+                }
+                .action(&CameraSettings::default()),
+            ),
+
+            DO_DIGICAM_CONTROL => self.act(CameraSettings::default().photo()),
+
+            IMAGE_START_CAPTURE if p2 > 0.0 => self.act(
+                TakeoffAction::Timelapse {
+                    period: p2.round().max(1.0) as usize,
+                }
+                .action(&CameraSettings::default()),
+            ),
+
+            IMAGE_START_CAPTURE => self.act(CameraSettings::default().photo()),
+
+            DO_SET_CAM_TRIGG_DIST if p1 > 0.0 => {
+                let period = (p1 / self.speed as f64).round().max(1.0) as usize;
+
+                self.lose(
+                    seq,
+                    item,
+                    format!(
+                        "a picture every {} m replaced by a picture every {} s, at {} m/s",
+                        p1, period, self.speed
+                    ),
+                );
+
+                self.act(TakeoffAction::Timelapse { period }.action(&CameraSettings::default()))
+            }
+
+            IMAGE_STOP_CAPTURE | DO_SET_CAM_TRIGG_DIST => self.act(Action::ImageStopCapture),
+
+            VIDEO_START_CAPTURE => self.act(CameraSettings::default().video_start()),
+            VIDEO_STOP_CAPTURE => self.act(Action::VideoStopCapture),
+
+            DO_JUMP => self.lose(seq, item, "jumps are not supported, ignored"),
+
+            _ => self.lose(seq, item, "not supported, ignored"),
+        }
+
+        Ok(())
+    }
+
+    fn lose(&mut self, seq: usize, item: &MissionItem, reason: impl Into<String>) {
+        self.losses.push(Loss {
+            seq,
+            command: item.command,
+            reason: reason.into(),
+        })
+    }
+
+    /// Actions happen at the last waypoint reached, or at the first one.
+    fn act(&mut self, action: Action) {
+        match self.waypoints.last_mut() {
+            Some(waypoint) => waypoint.actions.get_or_insert_with(Vec::new).push(action),
+            None => self.pending.push(action),
+        }
+    }
+
+    /// Altitude relative to the home position.
+    fn altitude(&mut self, seq: usize, item: &MissionItem) -> f64 {
+        let reason = match (item.frame, self.home_altitude) {
+            (frame::GLOBAL_RELATIVE_ALT | frame::GLOBAL_RELATIVE_ALT_INT, _) => None,
+
+            (frame::GLOBAL | frame::GLOBAL_INT, Some(home)) => return item.z - home,
+
+            (frame::GLOBAL | frame::GLOBAL_INT, None) => {
+                Some("altitude above sea level without home position, taken as relative")
+            }
+
+            (frame::GLOBAL_TERRAIN_ALT | frame::GLOBAL_TERRAIN_ALT_INT, _) => {
+                Some("altitude above terrain taken as relative to takeoff")
+            }
+
+            _ => Some("unknown frame, altitude taken as relative"),
+        };
+
+        if let Some(reason) = reason {
+            self.lose(seq, item, reason);
+        }
+
+        item.z
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        flightplan::Action,
+        mavlink::{command::*, MissionItem},
+    };
+
+    #[test]
+    fn absolute_altitudes_are_relative_to_home() {
+        let items = [
+            MissionItem::home(48.8583, 2.2945, 100.0),
+            MissionItem::at(NAV_TAKEOFF, [0.0; 4], 0.0, 0.0, 20.0),
+            MissionItem {
+                frame: crate::mavlink::frame::GLOBAL,
+                ..MissionItem::at(NAV_WAYPOINT, [5.0, 0.0, 0.0, 0.0], 48.8583, 2.2946, 130.0)
+            },
+            MissionItem::command(DO_JUMP, [1.0, 2.0, 0.0, 0.0]),
+            MissionItem::at(NAV_WAYPOINT, [0.0; 4], 48.8584, 2.2946, 40.0),
+        ];
+
+        let (flightplan, losses) = super::flightplan(&items, 1.0).unwrap();
+
+        let waypoints = &flightplan.plan.waypoints;
+
+        assert_eq!(waypoints.len(), 2);
+        assert_eq!(waypoints[0].altitude, 30);
+        assert_eq!(waypoints[1].altitude, 40);
+
+        assert!(matches!(
+            waypoints[0].actions.as_deref(),
+            Some([Action::Delay { delay: 5 }])
+        ));
+
+        // Heading north, to the next waypoint
+        assert!(waypoints[0].yaw.abs() < 1e-6);
+
+        assert_eq!(losses.len(), 1);
+        assert_eq!(losses[0].seq, 3);
+        assert_eq!(losses[0].command, DO_JUMP);
+    }
+
+    #[test]
+    fn mission_planner_regions_of_interest_are_points_of_interest() {
+        let file = "QGC WPL 110
+0\t1\t0\t16\t0\t0\t0\t0\t-35.363261\t149.165230\t584.090000\t1
+1\t0\t3\t201\t0.000000\t0.000000\t0.000000\t0.000000\t-35.362440\t149.165840\t0.000000\t1
+2\t0\t3\t16\t0.000000\t0.000000\t0.000000\t0.000000\t-35.362880\t149.165100\t30.000000\t1
+3\t0\t3\t201\t0.000000\t0.000000\t0.000000\t0.000000\t0.000000\t0.000000\t0.000000\t1
+4\t0\t3\t16\t0.000000\t0.000000\t0.000000\t0.000000\t-35.363500\t149.165600\t30.000000\t1
+";
+
+        let items = crate::mavlink::wpl::from_reader(file.as_bytes()).unwrap();
+
+        let (flightplan, losses) = super::flightplan(&items, 1.0).unwrap();
+
+        let pois: Vec<_> = flightplan
+            .plan
+            .waypoints
+            .iter()
+            .map(|waypoint| waypoint.poi)
+            .collect();
+
+        assert!(losses.is_empty());
+        assert_eq!(flightplan.plan.poi.len(), 1);
+        assert_eq!(flightplan.plan.poi[0].latitude, -35.36244);
+        assert_eq!(pois, [Some(0), None]);
+    }
+}
@@ -0,0 +1,63 @@
+use super::FlightPlan;
+
+/// Mean Earth radius in meters.
+const EARTH_RADIUS_M: f64 = 6_371_008.8;
+
+/// Great-circle distance in meters between two points, using the haversine formula.
+pub fn distance(latitude_a: f64, longitude_a: f64, latitude_b: f64, longitude_b: f64) -> f64 {
+    let d_latitude = (latitude_b - latitude_a).to_radians();
+    let d_longitude = (longitude_b - longitude_a).to_radians();
+
+    let h = (d_latitude / 2.0).sin().powi(2)
+        + latitude_a.to_radians().cos()
+            * latitude_b.to_radians().cos()
+            * (d_longitude / 2.0).sin().powi(2);
+
+    2.0 * EARTH_RADIUS_M * h.sqrt().asin()
+}
+
+/// Initial bearing in degrees, clockwise from north, to go from point A to point B.
+pub fn bearing(latitude_a: f64, longitude_a: f64, latitude_b: f64, longitude_b: f64) -> f64 {
+    let (latitude_a, latitude_b) = (latitude_a.to_radians(), latitude_b.to_radians());
+    let d_longitude = (longitude_b - longitude_a).to_radians();
+
+    let y = d_longitude.sin() * latitude_b.cos();
+    let x = latitude_a.cos() * latitude_b.sin()
+        - latitude_a.sin() * latitude_b.cos() * d_longitude.cos();
+
+    y.atan2(x).to_degrees().rem_euclid(360.0)
+}
+
+/// Meters east and north of the origin, on a plane tangent to the Earth at the origin.
+/// Accurate enough over the few kilometers of a flight plan.
+pub fn project(
+    latitude: f64,
+    longitude: f64,
+    origin_latitude: f64,
+    origin_longitude: f64,
+) -> (f64, f64) {
+    let east = (longitude - origin_longitude).to_radians()
+        * origin_latitude.to_radians().cos()
+        * EARTH_RADIUS_M;
+    let north = (latitude - origin_latitude).to_radians() * EARTH_RADIUS_M;
+
+    (east, north)
+}
+
+impl<'f> FlightPlan<'f> {
+    /// Length in meters of the path going through all waypoints.
+    pub fn distance(&self) -> f64 {
+        self.plan
+            .waypoints
+            .windows(2)
+            .map(|pair| {
+                distance(
+                    pair[0].latitude,
+                    pair[0].longitude,
+                    pair[1].latitude,
+                    pair[1].longitude,
+                )
+            })
+            .sum()
+    }
+}
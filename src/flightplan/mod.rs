@@ -1,19 +1,41 @@
-use std::hash::Hash;
+use std::borrow::Cow;
 
 use chrono::prelude::*;
+use uuid::Uuid;
 
 mod color;
 mod from_bin;
 mod from_csv;
+mod from_geojson;
+mod from_gpx;
 mod from_kml;
+mod from_mavlink;
+mod geo;
 mod model;
+mod settings;
+mod to_geojson;
+mod to_gpx;
+mod to_html;
+mod to_kml;
+mod to_litchi;
+mod to_mavlink;
+mod to_qgc;
+mod to_svg;
+mod viewport;
 
 pub use color::POI_COLORS;
+pub use from_geojson::FeatureCollection;
+pub use from_gpx::Gpx;
+pub use from_mavlink::Loss;
+pub use geo::{bearing, distance};
 pub use model::*;
+pub use settings::*;
+pub use viewport::*;
 
 use crate::{
     error::Error,
     litchi::{bin, csv::de::MissionRecord, kml},
+    mavlink::MissionItem,
 };
 
 use crate::litchi::Action as LitchiAction;
@@ -21,33 +43,125 @@ use crate::litchi::Action as LitchiAction;
 const DEFAULT_SPEED_MS: u8 = 5;
 const DEFAULT_WAYPOINT_ALTITUDE_M: u16 = 3;
 
-pub fn from_csv<'t, 'f>(
+/// Namespace of the UUIDv5 derived from source missions.
+const UUID_NAMESPACE: Uuid = Uuid::from_u128(0x98d35a56_1f6a_405f_8dbb_15c5ac814567);
+
+/// A KML document to review the plans in, see [`FlightPlan::to_kml`].
+pub fn to_kml(flightplans: &[FlightPlan], takeoff_elevation: Option<f64>) -> ::kml::Kml {
+    to_kml::document(flightplans, takeoff_elevation)
+}
+
+/// `source` is the raw content of the mission file, used to derive the plan's UUID.
+pub fn from_csv<'f>(
     title: &str,
-    records: &'t [MissionRecord],
+    records: &[MissionRecord],
+    source: &[u8],
+    settings: &Settings,
 ) -> Result<FlightPlan<'f>, Error> {
-    let mut res: Result<FlightPlan, Error> = records.try_into();
+    let mut res = from_csv::flightplan(records, settings.poi_tolerance);
 
     if let Ok(flightplan) = res.as_mut() {
         flightplan.title = title.to_owned();
-        flightplan.uuid = title.to_owned();
+        flightplan.identify(source, settings.uuid);
+        flightplan.apply(settings);
+    }
+
+    res
+}
+
+/// `source` is the raw content of the mission file, used to derive the plan's UUID.
+pub fn from_kml<'f>(
+    mission: &kml::Mission,
+    source: &[u8],
+    settings: &Settings,
+) -> Result<FlightPlan<'f>, Error> {
+    let mut res: Result<FlightPlan, Error> = mission.try_into();
+
+    if let Ok(flightplan) = res.as_mut() {
+        flightplan.identify(source, settings.uuid);
+        flightplan.apply(settings);
+    }
+
+    res
+}
+
+/// `source` is the raw content of the GeoJSON file, used to derive the plan's UUID.
+/// `title` is used when the collection has no name.
+/// Takeoff actions carried by the collection are kept, unless the settings choose others.
+pub fn from_geojson<'f>(
+    title: &str,
+    collection: &FeatureCollection,
+    source: &[u8],
+    settings: &Settings,
+) -> Result<FlightPlan<'f>, Error> {
+    let mut res: Result<FlightPlan, Error> = collection.try_into();
+
+    if let Ok(flightplan) = res.as_mut() {
+        if flightplan.title.is_empty() {
+            flightplan.title = title.to_owned();
+        }
+
+        flightplan.identify(source, settings.uuid);
+        flightplan.apply_keeping_takeoff(settings, collection.takeoff());
+    }
+
+    res
+}
+
+/// `source` is the raw content of the GPX file, used to derive the plan's UUID.
+/// `title` is used when the route or track has no name.
+pub fn from_gpx<'f>(
+    title: &str,
+    gpx: &Gpx,
+    source: &[u8],
+    settings: &Settings,
+) -> Result<FlightPlan<'f>, Error> {
+    let mut res: Result<FlightPlan, Error> = gpx.try_into();
+
+    if let Ok(flightplan) = res.as_mut() {
+        if flightplan.title.is_empty() {
+            flightplan.title = title.to_owned();
+        }
+
+        flightplan.identify(source, settings.uuid);
+        flightplan.apply(settings);
     }
 
     res
 }
 
-pub fn from_kml<'m, 'f>(mission: &'m kml::Mission) -> Result<FlightPlan<'f>, Error> {
-    mission.try_into()
+/// `source` is the raw content of the waypoint file, used to derive the plan's UUID.
+/// Also returns what couldn't be converted as is.
+pub fn from_waypoints<'f>(
+    title: &str,
+    items: &[MissionItem],
+    source: &[u8],
+    settings: &Settings,
+) -> Result<(FlightPlan<'f>, Vec<Loss>), Error> {
+    let mut res = from_mavlink::flightplan(items, settings.poi_tolerance);
+
+    if let Ok((flightplan, _)) = res.as_mut() {
+        flightplan.title = title.to_owned();
+        flightplan.identify(source, settings.uuid);
+        flightplan.apply(settings);
+    }
+
+    res
 }
 
-pub fn from_bin<'m, 'f>(
+/// `source` is the raw content of the mission file, used to derive the plan's UUID.
+pub fn from_bin<'f>(
     title: &str,
-    mission: &'m bin::LitchiMission,
+    mission: &bin::LitchiMission,
+    source: &[u8],
+    settings: &Settings,
 ) -> Result<FlightPlan<'f>, Error> {
     let mut res: Result<FlightPlan, Error> = mission.try_into();
 
     if let Ok(flightplan) = res.as_mut() {
         flightplan.title = title.to_owned();
-        flightplan.uuid = title.to_owned();
+        flightplan.identify(source, settings.uuid);
+        flightplan.apply(settings);
     }
 
     res
@@ -69,30 +183,17 @@ impl<'f> From<&'_ FlightPlan<'f>> for Vec<u8> {
     }
 }
 
-pub mod defaults {
-
-    use super::Action;
-
-    pub const _4K_30FPS_RECORDING: Action = Action::VideoStartCapture {
-        camera_id: 0,
-        resolution: 2073600,
-        fps: 30,
-    };
-}
-
 impl<'f> FlightPlan<'f> {
-    fn new(title: &str, latitude: f64, longitude: f64) -> Self {
+    pub(crate) fn new(title: &str, latitude: f64, longitude: f64) -> Self {
         let now = Utc::now();
 
         let title = title.to_owned();
-        let uuid = title.to_owned();
+        let uuid = Uuid::new_v4().to_string();
         let date = now.timestamp_millis() as u64;
 
-        let takeoff = vec![defaults::_4K_30FPS_RECORDING];
-
         FlightPlan {
             version: 1,
-            product: "ANAFI_4K",
+            product: Cow::Borrowed("ANAFI_4K"),
             product_id: 2324,
 
             title,
@@ -116,34 +217,140 @@ impl<'f> FlightPlan<'f> {
             map_type: 4,
 
             plan: model::Plan {
-                takeoff,
+                takeoff: vec![],
                 poi: vec![],
                 waypoints: vec![],
             },
         }
     }
-}
 
-impl PartialEq for PointOfInterest {
-    fn eq(&self, other: &Self) -> bool {
-        let same_latitude = (other.latitude - self.latitude).abs() < 0.0001;
-        let same_longitude = (other.longitude - self.longitude).abs() < 0.0001;
-        let same_altitude = (other.altitude - self.altitude) == 0;
+    fn identify(&mut self, source: &[u8], mode: UuidMode) {
+        let uuid = match mode {
+            UuidMode::Content => Uuid::new_v5(&UUID_NAMESPACE, source),
+            UuidMode::Random => Uuid::new_v4(),
+        };
 
-        same_latitude && same_longitude && same_altitude
+        self.uuid = uuid.to_string();
+    }
+
+    /// Whether this plan was converted by litchi2fp from a mission file.
+    /// FreeFlight itself generates random (version 4) UUIDs, while converted plans get a
+    /// version 5 UUID, unless converted with [`UuidMode::Random`].
+    /// Plans converted with random UUIDs, as did litchi2fp before content derived ones,
+    /// can't be told apart from those made on the tablet and are not recognized.
+    pub fn is_converted(&self) -> bool {
+        Uuid::parse_str(&self.uuid).is_ok_and(|uuid| uuid.get_version_num() == 5)
+    }
+
+    /// Gives this plan a new random UUID, e.g. to store it next to the original.
+    pub fn renew_uuid(&mut self) {
+        self.identify(&[], UuidMode::Random);
+    }
+
+    /// Whether both plans are the same, whenever they were generated.
+    pub fn same_content(&self, other: &FlightPlan) -> bool {
+        let without_date = |flightplan: &FlightPlan| {
+            let mut value = serde_json::to_value(flightplan).ok();
+
+            if let Some(serde_json::Value::Object(fields)) = &mut value {
+                fields.remove("date");
+            }
+
+            value
+        };
+
+        without_date(self) == without_date(other)
+    }
+
+    /// Name of the folder holding this plan on the device,
+    /// safe to use whatever the plan's UUID is made of.
+    pub fn folder_name(&self) -> String {
+        sanitize_file_name(&self.uuid)
+    }
+
+    /// Applies conversion settings to a freshly converted flight plan.
+    fn apply(&mut self, settings: &Settings) {
+        self.apply_keeping_takeoff(settings, None);
+    }
+
+    /// Applies conversion settings, keeping the takeoff actions `carried` by the input
+    /// unless the settings choose others.
+    fn apply_keeping_takeoff(&mut self, settings: &Settings, carried: Option<Vec<Action>>) {
+        self.plan.takeoff = match (&settings.takeoff, carried) {
+            (Some(takeoff), _) => takeoff.actions(&settings.camera),
+            (None, Some(actions)) => actions,
+            (None, None) => Takeoff::default().actions(&settings.camera),
+        };
+
+        let waypoint_actions = self
+            .plan
+            .waypoints
+            .iter_mut()
+            .filter_map(|waypoint| waypoint.actions.as_mut())
+            .flatten();
+
+        for action in self.plan.takeoff.iter_mut().chain(waypoint_actions) {
+            settings.camera.apply(action);
+        }
+
+        self.stop_running_captures();
+
+        self.frame(&settings.viewport);
     }
-}
 
-impl Eq for PointOfInterest {}
+    /// Stops, at the last waypoint, any capture still running at the end of the plan.
+    fn stop_running_captures(&mut self) {
+        let mut recording = false;
+        let mut timelapse = false;
+
+        let waypoint_actions = self
+            .plan
+            .waypoints
+            .iter()
+            .filter_map(|waypoint| waypoint.actions.as_ref())
+            .flatten();
+
+        for action in self.plan.takeoff.iter().chain(waypoint_actions) {
+            match action {
+                Action::VideoStartCapture { .. } => recording = true,
+                Action::VideoStopCapture => recording = false,
+
+                // A single picture has no period and stops by itself.
+                Action::ImageStartCapture { period, .. } if *period > 0 => timelapse = true,
+                Action::ImageStopCapture => timelapse = false,
+
+                _ => (),
+            }
+        }
 
-impl Hash for PointOfInterest {
-    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        let tuple = (self.latitude.to_bits(), self.longitude.to_bits());
+        let mut stops: Vec<_> = [
+            (recording, Action::VideoStopCapture),
+            (timelapse, Action::ImageStopCapture),
+        ]
+        .into_iter()
+        .filter_map(|(running, stop)| running.then_some(stop))
+        .collect();
 
-        tuple.hash(state);
+        if let (Some(last), false) = (self.plan.waypoints.last_mut(), stops.is_empty()) {
+            last.actions.get_or_insert_with(Vec::new).append(&mut stops);
+        }
+    }
+}
 
-        self.altitude.hash(state);
-        self.color.hash(state);
+/// Replaces characters that can't be used in a file or folder name.
+pub fn sanitize_file_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| match c {
+            c if c.is_alphanumeric() => c,
+            '-' | '_' | '.' | ' ' => c,
+            _ => '_',
+        })
+        .collect();
+
+    match name.trim_matches(|c| c == '.' || c == ' ') {
+        "" => "_".to_owned(),
+        name => name.to_owned(),
     }
 }
 
@@ -154,13 +361,9 @@ impl From<&'_ LitchiAction> for Action {
         match action {
             StayFor { ms } => Action::Delay { delay: ms / 1000 },
 
-            TakePhoto => Action::ImageStartCapture {
-                period: 0,
-                resolution: 14.0,
-                nb_of_pictures: 1,
-            },
+            TakePhoto => CameraSettings::default().photo(),
 
-            StartRecording => crate::flightplan::defaults::_4K_30FPS_RECORDING,
+            StartRecording => CameraSettings::default().video_start(),
 
             StopRecording => Action::VideoStopCapture,
 
@@ -176,3 +379,186 @@ impl From<&'_ LitchiAction> for Action {
         }
     }
 }
+
+/// Waypoints of tests: at 30 m, flown at 5 m/s, with a yaw of 0 and without any action.
+#[cfg(test)]
+impl Waypoint {
+    pub(crate) fn at(latitude: f64, longitude: f64) -> Self {
+        Waypoint {
+            latitude,
+            longitude,
+            altitude: 30,
+            yaw: 0.0,
+            speed: DEFAULT_SPEED_MS,
+            poi: None,
+            dont_stop: true,
+            follow_poi: false,
+            follow: 1,
+            last_yaw: 0.0,
+            actions: None,
+        }
+    }
+
+    pub(crate) fn with_altitude(self, altitude: u16) -> Self {
+        Waypoint { altitude, ..self }
+    }
+
+    pub(crate) fn with_yaw(self, yaw: f64) -> Self {
+        Waypoint { yaw, ..self }
+    }
+
+    pub(crate) fn with_speed(self, speed: u8) -> Self {
+        Waypoint { speed, ..self }
+    }
+
+    pub(crate) fn with_poi(self, poi: Option<u8>) -> Self {
+        Waypoint {
+            poi,
+            follow_poi: poi.is_some(),
+            ..self
+        }
+    }
+
+    /// No actions at all when `actions` is empty.
+    pub(crate) fn with_actions(self, actions: Vec<Action>) -> Self {
+        Waypoint {
+            actions: (!actions.is_empty()).then_some(actions),
+            ..self
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        Action, FlightPlan, PointOfInterest, Settings, Takeoff, TakeoffAction, UuidMode, Waypoint,
+    };
+
+    fn waypoint(actions: Vec<Action>) -> Waypoint {
+        Waypoint::at(48.8566, 2.3522).with_actions(actions)
+    }
+
+    fn last_actions(settings: &Settings, waypoints: Vec<Waypoint>) -> Option<Vec<Action>> {
+        let mut flightplan = FlightPlan::new("test", 48.8566, 2.3522);
+
+        flightplan.plan.waypoints = waypoints;
+        flightplan.apply(settings);
+
+        flightplan.plan.waypoints.pop().and_then(|w| w.actions)
+    }
+
+    #[test]
+    fn photo_only_mission_is_not_stopped() {
+        let settings = Settings {
+            takeoff: Some(Takeoff(vec![])),
+            ..Default::default()
+        };
+
+        let photo = settings.camera.photo();
+
+        let actions = last_actions(&settings, vec![waypoint(vec![photo]), waypoint(vec![])]);
+
+        assert!(actions.is_none());
+    }
+
+    #[test]
+    fn running_captures_are_stopped_after_existing_actions() {
+        let settings = Settings {
+            takeoff: Some("video,timelapse:5".parse().unwrap()),
+            ..Default::default()
+        };
+
+        let actions =
+            last_actions(&settings, vec![waypoint(vec![Action::Delay { delay: 2 }])]).unwrap();
+
+        assert!(matches!(
+            actions.as_slice(),
+            [
+                Action::Delay { delay: 2 },
+                Action::VideoStopCapture,
+                Action::ImageStopCapture
+            ]
+        ));
+    }
+
+    #[test]
+    fn stopped_recording_is_not_stopped_twice() {
+        let settings = Settings::default();
+
+        assert_eq!(settings.takeoff, None);
+        assert_eq!(Takeoff::default(), Takeoff(vec![TakeoffAction::Video]));
+
+        let actions = last_actions(
+            &settings,
+            vec![waypoint(vec![Action::VideoStopCapture]), waypoint(vec![])],
+        );
+
+        assert!(actions.is_none());
+    }
+
+    #[test]
+    fn content_uuid_is_stable() {
+        let mut a = FlightPlan::new("a", 0.0, 0.0);
+        let mut b = FlightPlan::new("b", 0.0, 0.0);
+
+        a.identify(b"mission", UuidMode::Content);
+        b.identify(b"mission", UuidMode::Content);
+
+        assert_eq!(a.uuid, b.uuid);
+
+        b.identify(b"mission", UuidMode::Random);
+
+        assert_ne!(a.uuid, b.uuid);
+    }
+
+    #[test]
+    fn folder_name_is_sanitized() {
+        let mut flightplan = FlightPlan::new("", 0.0, 0.0);
+
+        flightplan.uuid = "../Tour/Eiffel: 2*".to_owned();
+        assert_eq!(flightplan.folder_name(), "_Tour_Eiffel_ 2_");
+
+        flightplan.uuid = "..".to_owned();
+        assert_eq!(flightplan.folder_name(), "_");
+    }
+
+    #[test]
+    fn the_last_point_of_interest_is_numbered_256() {
+        let mut flightplan = FlightPlan::new("test", 48.8566, 2.3522);
+
+        flightplan.plan.poi = (0..256)
+            .map(|_| PointOfInterest {
+                latitude: 48.857,
+                longitude: 2.353,
+                altitude: 10,
+                color: 0,
+            })
+            .collect();
+
+        flightplan.plan.waypoints = vec![Waypoint::at(48.8566, 2.3522).with_poi(Some(255))];
+
+        let kml = match flightplan.to_kml(None) {
+            ::kml::Kml::Folder { elements, .. } => elements,
+            other => panic!("not a folder: {:?}", other),
+        };
+
+        assert!(kml.iter().any(|element| matches!(
+            element,
+            ::kml::Kml::Placemark(p) if p.name.as_deref() == Some("1 → POI 256")
+        )));
+
+        let geojson = flightplan.to_geojson();
+
+        assert_eq!(geojson["features"][1]["properties"]["poi"], 256);
+
+        let mut gpx = vec![];
+
+        flightplan.to_gpx(None, &mut gpx).unwrap();
+
+        assert!(String::from_utf8(gpx)
+            .unwrap()
+            .contains("<ff:poi>256</ff:poi>"));
+
+        assert!(flightplan.to_html("test.csv", &[]).contains("POI 256"));
+    }
+}
@@ -1,10 +1,12 @@
+use std::{borrow::Cow, fmt::Display};
+
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct FlightPlan<'f> {
     pub version: u8,
     pub title: String,
-    pub product: &'f str,
+    pub product: Cow<'f, str>,
 
     #[serde(rename = "productId")]
     pub product_id: u16,
@@ -36,7 +38,7 @@ pub struct FlightPlan<'f> {
     pub plan: Plan,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Plan {
     pub takeoff: Vec<Action>,
     pub poi: Vec<PointOfInterest>,
@@ -45,7 +47,7 @@ pub struct Plan {
     pub waypoints: Vec<Waypoint>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct PointOfInterest {
     pub latitude: f64,
     pub longitude: f64,
@@ -53,7 +55,7 @@ pub struct PointOfInterest {
     pub color: i32,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Waypoint {
     pub latitude: f64,
     pub longitude: f64,
@@ -78,7 +80,7 @@ pub struct Waypoint {
     pub actions: Option<Vec<Action>>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum Action {
     /// Start video recording
@@ -149,3 +151,24 @@ pub enum Action {
 
     Landing,
 }
+
+/// Short description of the action, for humans.
+impl Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Action::VideoStartCapture { fps, .. } => write!(f, "start video at {} fps", fps),
+            Action::VideoStopCapture => write!(f, "stop video"),
+
+            Action::ImageStartCapture { period: 0, .. } => write!(f, "take a picture"),
+            Action::ImageStartCapture { period, .. } => {
+                write!(f, "take a picture every {} s", period)
+            }
+            Action::ImageStopCapture => write!(f, "stop pictures"),
+
+            Action::Delay { delay } => write!(f, "hover for {} s", delay),
+            Action::Tilt { angle, .. } => write!(f, "tilt camera to {}°", angle),
+            Action::Panorama { angle, .. } => write!(f, "panorama of {}°", angle),
+            Action::Landing => write!(f, "land"),
+        }
+    }
+}
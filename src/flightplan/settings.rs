@@ -0,0 +1,285 @@
+use std::str::FromStr;
+
+use clap::ValueEnum;
+use serde::{de, Deserialize, Deserializer};
+
+use super::{Action, Viewport};
+
+/// Conversion settings.
+/// Can be loaded from a JSON profile, then overridden from the command line.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub camera: CameraSettings,
+
+    /// Actions after takeoff, those carried by the input or [`Takeoff::default`] when not chosen
+    pub takeoff: Option<Takeoff>,
+
+    pub viewport: Viewport,
+
+    pub uuid: UuidMode,
+
+    /// Distance in meters under which two points of interest are the same
+    pub poi_tolerance: f64,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            camera: CameraSettings::default(),
+            takeoff: None,
+            viewport: Viewport::default(),
+            uuid: UuidMode::default(),
+            poi_tolerance: 1.0,
+        }
+    }
+}
+
+/// How the flight plan's UUID is generated.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UuidMode {
+    /// Derived from the source mission (UUIDv5):
+    /// converting the same mission again updates the same plan on the device.
+    #[default]
+    Content,
+
+    /// Random (UUIDv4): every conversion creates a new plan on the device.
+    Random,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct CameraSettings {
+    /// Resolution used for every video capture action
+    pub resolution: VideoResolution,
+
+    /// Frames per second used for every video capture action
+    pub fps: u8,
+
+    /// Mode used for every picture capture action
+    pub photo: PhotoMode,
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        CameraSettings {
+            resolution: VideoResolution::default(),
+            fps: 30,
+            photo: PhotoMode::default(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, ValueEnum)]
+pub enum VideoResolution {
+    /// 1920x1080, the value written by FreeFlight itself.
+    #[default]
+    #[serde(rename = "1080p")]
+    #[clap(name = "1080p")]
+    FullHd,
+
+    /// 2704x1520
+    #[serde(rename = "2.7k")]
+    #[clap(name = "2.7k")]
+    TwoPointSevenK,
+
+    /// 3840x2160
+    #[serde(rename = "4k")]
+    #[clap(name = "4k")]
+    UltraHd,
+
+    /// 4096x2160
+    #[serde(rename = "4k-cinema")]
+    #[clap(name = "4k-cinema")]
+    Cinema,
+}
+
+impl VideoResolution {
+    /// Resolution in pixels, as expected by `VideoStartCapture`
+    pub fn pixels(self) -> usize {
+        use VideoResolution::*;
+
+        match self {
+            FullHd => 1920 * 1080,
+            TwoPointSevenK => 2704 * 1520,
+            UltraHd => 3840 * 2160,
+            Cinema => 4096 * 2160,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum PhotoMode {
+    #[default]
+    Dng,
+    JpegRect,
+    JpegWide,
+}
+
+impl PhotoMode {
+    /// Value expected by `ImageStartCapture`, see [`Action::ImageStartCapture`]
+    pub fn resolution(self) -> f64 {
+        use PhotoMode::*;
+
+        match self {
+            Dng => 14.0,
+            JpegRect => 12.58291244506836,
+            JpegWide => 13.600000381469727,
+        }
+    }
+}
+
+impl CameraSettings {
+    pub fn video_start(&self) -> Action {
+        Action::VideoStartCapture {
+            camera_id: 0,
+            resolution: self.resolution.pixels(),
+            fps: self.fps,
+        }
+    }
+
+    pub fn photo(&self) -> Action {
+        Action::ImageStartCapture {
+            period: 0,
+            resolution: self.photo.resolution(),
+            nb_of_pictures: 1,
+        }
+    }
+
+    /// Applies these settings to a capture action, other actions are left untouched.
+    pub fn apply(&self, action: &mut Action) {
+        match action {
+            Action::VideoStartCapture {
+                resolution, fps, ..
+            } => {
+                *resolution = self.resolution.pixels();
+                *fps = self.fps;
+            }
+
+            Action::ImageStartCapture { resolution, .. } => {
+                *resolution = self.photo.resolution();
+            }
+
+            _ => (),
+        }
+    }
+}
+
+/// Actions performed right after takeoff, before reaching the first waypoint.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(transparent)]
+pub struct Takeoff(pub Vec<TakeoffAction>);
+
+impl Default for Takeoff {
+    fn default() -> Self {
+        Takeoff(vec![TakeoffAction::Video])
+    }
+}
+
+impl Takeoff {
+    pub fn actions(&self, camera: &CameraSettings) -> Vec<Action> {
+        self.0.iter().map(|action| action.action(camera)).collect()
+    }
+}
+
+/// Parses a comma separated list of actions, or `none`.
+impl FromStr for Takeoff {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim() == "none" {
+            return Ok(Takeoff(vec![]));
+        }
+
+        s.split(',')
+            .map(TakeoffAction::from_str)
+            .collect::<Result<_, _>>()
+            .map(Takeoff)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TakeoffAction {
+    /// Start recording a video
+    Video,
+
+    /// Start taking a picture every `period` seconds
+    Timelapse { period: usize },
+
+    /// Tilt the gimbal to `angle` degrees
+    Tilt { angle: i8 },
+}
+
+const DEFAULT_TIMELAPSE_PERIOD_S: usize = 2;
+const DEFAULT_TILT_SPEED: u8 = 10;
+
+impl TakeoffAction {
+    pub fn action(&self, camera: &CameraSettings) -> Action {
+        match *self {
+            TakeoffAction::Video => camera.video_start(),
+
+            TakeoffAction::Timelapse { period } => Action::ImageStartCapture {
+                period,
+                resolution: camera.photo.resolution(),
+                nb_of_pictures: 0,
+            },
+
+            TakeoffAction::Tilt { angle } => Action::Tilt {
+                angle,
+                speed: DEFAULT_TILT_SPEED,
+            },
+        }
+    }
+}
+
+/// Parses `video`, `timelapse[:<seconds>]` or `tilt:<degrees>`.
+impl FromStr for TakeoffAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, value) = match s.trim().split_once(':') {
+            Some((name, value)) => (name, Some(value)),
+            None => (s.trim(), None),
+        };
+
+        let invalid = |value: &str| format!("invalid value for {}: '{}'", name, value);
+
+        match (name, value) {
+            ("video", None) => Ok(TakeoffAction::Video),
+
+            ("timelapse", None) => Ok(TakeoffAction::Timelapse {
+                period: DEFAULT_TIMELAPSE_PERIOD_S,
+            }),
+
+            ("timelapse", Some(period)) => match period.parse() {
+                Ok(period) if period > 0 => Ok(TakeoffAction::Timelapse { period }),
+                _ => Err(invalid(period)),
+            },
+
+            ("tilt", Some(angle)) => match angle.parse() {
+                Ok(angle @ -90..=90) => Ok(TakeoffAction::Tilt { angle }),
+                _ => Err(invalid(angle)),
+            },
+
+            ("tilt", None) => Err("tilt requires an angle, e.g. 'tilt:-90'".to_owned()),
+
+            _ => Err(format!(
+                "unknown takeoff action '{}', expected one of: video, timelapse[:<seconds>], tilt:<degrees>",
+                s
+            )),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for TakeoffAction {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let text = String::deserialize(deserializer)?;
+
+        text.parse().map_err(de::Error::custom)
+    }
+}
@@ -0,0 +1,76 @@
+use serde_json::{json, Value};
+
+use super::FlightPlan;
+
+impl<'f> FlightPlan<'f> {
+    /// A GeoJSON `FeatureCollection`: the path as a `LineString`, then a `Point` per waypoint
+    /// and per point of interest, told apart by their `kind` property.
+    /// Waypoints and points of interest are numbered from 1, altitudes are relative to takeoff.
+    /// Takeoff actions are written in the collection's own `properties`.
+    pub fn to_geojson(&self) -> Value {
+        let waypoints = &self.plan.waypoints;
+
+        let path = json!({
+            "type": "Feature",
+            "geometry": {
+                "type": "LineString",
+                "coordinates": waypoints
+                    .iter()
+                    .map(|w| [w.longitude, w.latitude, w.altitude as f64])
+                    .collect::<Vec<_>>(),
+            },
+            "properties": {
+                "kind": "path",
+                "title": self.title,
+            },
+        });
+
+        let waypoints = waypoints.iter().enumerate().map(|(index, waypoint)| {
+            json!({
+                "type": "Feature",
+                "geometry": {
+                    "type": "Point",
+                    "coordinates": [waypoint.longitude, waypoint.latitude, waypoint.altitude],
+                },
+                "properties": {
+                    "kind": "waypoint",
+                    "number": index + 1,
+                    "altitude": waypoint.altitude,
+                    "yaw": waypoint.yaw,
+                    "speed": waypoint.speed,
+                    "poi": waypoint.poi.map(|poi| u16::from(poi) + 1),
+                    "continue": waypoint.dont_stop,
+                    "actions": waypoint.actions.as_deref().unwrap_or_default(),
+                },
+            })
+        });
+
+        let pois = self.plan.poi.iter().enumerate().map(|(index, poi)| {
+            json!({
+                "type": "Feature",
+                "geometry": {
+                    "type": "Point",
+                    "coordinates": [poi.longitude, poi.latitude, poi.altitude],
+                },
+                "properties": {
+                    "kind": "poi",
+                    "number": index + 1,
+                    "altitude": poi.altitude,
+                    "color": poi.color,
+                },
+            })
+        });
+
+        json!({
+            "type": "FeatureCollection",
+            "name": self.title,
+            "properties": {
+                "takeoff": self.plan.takeoff,
+            },
+            "features": std::iter::once(path)
+                .chain(waypoints)
+                .chain(pois)
+                .collect::<Vec<_>>(),
+        })
+    }
+}
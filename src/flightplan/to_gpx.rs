@@ -0,0 +1,138 @@
+use std::io::Write;
+
+use quick_xml::{
+    events::{BytesDecl, BytesEnd, BytesStart, BytesText, Event},
+    Writer,
+};
+
+use crate::error::Error;
+
+use super::{from_gpx::POI_TYPE, FlightPlan};
+
+const GPX_NAMESPACE: &str = "http://www.topografix.com/GPX/1/1";
+
+/// Namespace of the extensions carrying what GPX has no room for.
+const EXTENSIONS_NAMESPACE: &str = "urn:litchi2fp:gpx:1";
+
+impl<'f> FlightPlan<'f> {
+    /// Writes the plan as a GPX route, its points of interest as waypoints of type `poi`.
+    ///
+    /// GPX elevations are above sea level, so they are only written given the
+    /// elevation of the takeoff point. Altitudes relative to takeoff, yaw, speed,
+    /// point of interest and actions are written as `ff:` extensions.
+    pub fn to_gpx<W: Write>(&self, takeoff_elevation: Option<f64>, writer: W) -> Result<(), Error> {
+        let mut writer = GpxWriter(Writer::new_with_indent(writer, b' ', 2));
+
+        writer.event(Event::Decl(BytesDecl::new(b"1.0", Some(b"UTF-8"), None)))?;
+
+        writer.start(
+            "gpx",
+            &[
+                ("version", "1.1"),
+                ("creator", "litchi2fp"),
+                ("xmlns", GPX_NAMESPACE),
+                ("xmlns:ff", EXTENSIONS_NAMESPACE),
+            ],
+        )?;
+
+        for (index, poi) in self.plan.poi.iter().enumerate() {
+            writer.start_point("wpt", poi.latitude, poi.longitude)?;
+
+            if let Some(elevation) = takeoff_elevation {
+                writer.text("ele", &(elevation + poi.altitude as f64).to_string())?;
+            }
+
+            writer.text("name", &format!("POI {}", index + 1))?;
+            writer.text("type", POI_TYPE)?;
+
+            writer.start("extensions", &[])?;
+            writer.text("ff:altitude", &poi.altitude.to_string())?;
+            writer.text("ff:color", &poi.color.to_string())?;
+            writer.end("extensions")?;
+
+            writer.end("wpt")?;
+        }
+
+        writer.start("rte", &[])?;
+        writer.text("name", &self.title)?;
+
+        for (index, waypoint) in self.plan.waypoints.iter().enumerate() {
+            writer.start_point("rtept", waypoint.latitude, waypoint.longitude)?;
+
+            if let Some(elevation) = takeoff_elevation {
+                writer.text("ele", &(elevation + waypoint.altitude as f64).to_string())?;
+            }
+
+            writer.text("name", &(index + 1).to_string())?;
+
+            let actions = waypoint.actions.as_deref().unwrap_or_default();
+
+            if !actions.is_empty() {
+                let description: Vec<_> = actions.iter().map(ToString::to_string).collect();
+
+                writer.text("desc", &description.join(", "))?;
+            }
+
+            writer.start("extensions", &[])?;
+            writer.text("ff:altitude", &waypoint.altitude.to_string())?;
+            writer.text("ff:yaw", &waypoint.yaw.to_string())?;
+            writer.text("ff:speed", &waypoint.speed.to_string())?;
+
+            if let Some(poi) = waypoint.poi {
+                writer.text("ff:poi", &(u16::from(poi) + 1).to_string())?;
+            }
+
+            if let Some(actions) = &waypoint.actions {
+                let actions = serde_json::to_string(actions).unwrap_or_default();
+
+                writer.text("ff:actions", &actions)?;
+            }
+
+            writer.end("extensions")?;
+
+            writer.end("rtept")?;
+        }
+
+        writer.end("rte")?;
+        writer.end("gpx")?;
+
+        Ok(())
+    }
+}
+
+struct GpxWriter<W: Write>(Writer<W>);
+
+impl<W: Write> GpxWriter<W> {
+    fn event(&mut self, event: Event) -> Result<(), Error> {
+        Ok(self.0.write_event(event)?)
+    }
+
+    fn start(&mut self, name: &str, attributes: &[(&str, &str)]) -> Result<(), Error> {
+        let start = BytesStart::borrowed_name(name.as_bytes());
+
+        self.event(Event::Start(
+            start.with_attributes(attributes.iter().copied()),
+        ))
+    }
+
+    fn start_point(&mut self, name: &str, latitude: f64, longitude: f64) -> Result<(), Error> {
+        self.start(
+            name,
+            &[
+                ("lat", &latitude.to_string()),
+                ("lon", &longitude.to_string()),
+            ],
+        )
+    }
+
+    fn end(&mut self, name: &str) -> Result<(), Error> {
+        self.event(Event::End(BytesEnd::borrowed(name.as_bytes())))
+    }
+
+    /// An element holding only text.
+    fn text(&mut self, name: &str, text: &str) -> Result<(), Error> {
+        self.start(name, &[])?;
+        self.event(Event::Text(BytesText::from_plain_str(text)))?;
+        self.end(name)
+    }
+}
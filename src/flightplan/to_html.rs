@@ -0,0 +1,274 @@
+use sha2::{Digest, Sha256};
+
+use super::{distance, to_svg::text, Action, BoundingBox, FlightPlan};
+
+const PROFILE_WIDTH: f64 = 800.0;
+const PROFILE_HEIGHT: f64 = 240.0;
+const PROFILE_MARGIN: f64 = 50.0;
+
+/// Altitude in meters under which profiles are not enlarged further
+const MIN_PROFILE_ALTITUDE_M: f64 = 10.0;
+
+const STYLE: &str = "\
+body { font-family: sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; margin: 1em 0; }
+th, td { border: 1px solid #ccc; padding: 0.3em 0.6em; text-align: left; }
+td.number { text-align: right; font-variant-numeric: tabular-nums; }
+code { word-break: break-all; }
+svg { max-width: 100%; height: auto; border: 1px solid #ccc; }";
+
+impl<'f> FlightPlan<'f> {
+    /// A report standing on its own, without any asset to fetch: plan view, altitude
+    /// profile and table of the waypoints, with the name and SHA-256 of the source file.
+    pub fn to_html(&self, source_name: &str, source: &[u8]) -> String {
+        let hash = format!("{:x}", Sha256::digest(source));
+
+        let mut html = format!(
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>{title}</title>\n<style>\n{style}\n</style>\n</head>\n<body>\n\
+             <h1>{title}</h1>\n",
+            title = text(&self.title),
+            style = STYLE
+        );
+
+        let mut summary = vec![
+            ("Source file", text(source_name)),
+            ("SHA-256", format!("<code>{}</code>", hash)),
+            ("UUID", format!("<code>{}</code>", self.uuid)),
+            ("Waypoints", self.plan.waypoints.len().to_string()),
+            ("Distance", format!("{:.0} m", self.distance())),
+            ("Estimated duration", duration(self.duration())),
+        ];
+
+        if let Some(bbox) = BoundingBox::of(self) {
+            summary.push((
+                "Bounding box",
+                format!(
+                    "{:.6}, {:.6} to {:.6}, {:.6} (south west to north east)",
+                    bbox.south, bbox.west, bbox.north, bbox.east
+                ),
+            ));
+        }
+
+        if !self.plan.takeoff.is_empty() {
+            summary.push(("At takeoff", actions(&self.plan.takeoff)));
+        }
+
+        html += "<table>\n";
+
+        for (name, value) in summary {
+            html += &format!("<tr><th>{}</th><td>{}</td></tr>\n", name, value);
+        }
+
+        html += "</table>\n";
+
+        html += "<h2>Plan view</h2>\n";
+        html += &self.to_svg();
+
+        html += "<h2>Altitude profile</h2>\n";
+        html += &self.profile();
+
+        html += "<h2>Waypoints</h2>\n<table>\n<tr><th>#</th><th>Latitude</th><th>Longitude</th>\
+                 <th>Altitude (m)</th><th>Speed (m/s)</th><th>Yaw (°)</th><th>POI</th>\
+                 <th>Actions</th></tr>\n";
+
+        for (index, waypoint) in self.plan.waypoints.iter().enumerate() {
+            let poi = match waypoint.poi {
+                Some(poi) => format!("POI {}", u16::from(poi) + 1),
+                None => String::new(),
+            };
+
+            html += &format!(
+                "<tr><td class=\"number\">{}</td><td class=\"number\">{:.6}</td>\
+                 <td class=\"number\">{:.6}</td><td class=\"number\">{}</td>\
+                 <td class=\"number\">{}</td><td class=\"number\">{:.0}</td><td>{}</td>\
+                 <td>{}</td></tr>\n",
+                index + 1,
+                waypoint.latitude,
+                waypoint.longitude,
+                waypoint.altitude,
+                waypoint.speed,
+                waypoint.yaw,
+                poi,
+                actions(waypoint.actions.as_deref().unwrap_or_default())
+            );
+        }
+
+        html += "</table>\n</body>\n</html>\n";
+
+        html
+    }
+
+    /// Seconds to fly from the first waypoint to the last one, each leg at the speed of
+    /// the waypoint it reaches, plus the delays. Climbs and turns are not accounted for.
+    fn duration(&self) -> f64 {
+        let waypoints = &self.plan.waypoints;
+
+        let legs: f64 = waypoints
+            .windows(2)
+            .map(|pair| {
+                let length = distance(
+                    pair[0].latitude,
+                    pair[0].longitude,
+                    pair[1].latitude,
+                    pair[1].longitude,
+                );
+
+                length / pair[1].speed.max(1) as f64
+            })
+            .sum();
+
+        let delays: usize = waypoints
+            .iter()
+            .flat_map(|waypoint| waypoint.actions.iter().flatten())
+            .chain(&self.plan.takeoff)
+            .map(|action| match action {
+                Action::Delay { delay } => *delay,
+                _ => 0,
+            })
+            .sum();
+
+        legs + delays as f64
+    }
+
+    /// Altitude of the waypoints along the distance flown, as SVG.
+    fn profile(&self) -> String {
+        let waypoints = &self.plan.waypoints;
+
+        let mut along = 0.0;
+
+        let points: Vec<_> = waypoints
+            .iter()
+            .enumerate()
+            .map(|(index, waypoint)| {
+                if let Some(previous) = index.checked_sub(1).map(|i| &waypoints[i]) {
+                    along += distance(
+                        previous.latitude,
+                        previous.longitude,
+                        waypoint.latitude,
+                        waypoint.longitude,
+                    );
+                }
+
+                (along, waypoint.altitude as f64)
+            })
+            .collect();
+
+        let length = along.max(1.0);
+
+        let highest = points
+            .iter()
+            .map(|(_, altitude)| *altitude)
+            .fold(MIN_PROFILE_ALTITUDE_M, f64::max);
+
+        let x =
+            |along: f64| PROFILE_MARGIN + along / length * (PROFILE_WIDTH - 2.0 * PROFILE_MARGIN);
+        let y = |altitude: f64| {
+            PROFILE_HEIGHT
+                - PROFILE_MARGIN
+                - altitude / highest * (PROFILE_HEIGHT - 2.0 * PROFILE_MARGIN)
+        };
+
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" \
+             viewBox=\"0 0 {w} {h}\" font-family=\"sans-serif\" font-size=\"12\">\n\
+             <rect width=\"100%\" height=\"100%\" fill=\"white\"/>\n",
+            w = PROFILE_WIDTH,
+            h = PROFILE_HEIGHT
+        );
+
+        // Axes, from the highest altitude down to takeoff then along the distance flown
+        svg += &format!(
+            "<path d=\"M {left:.1} {top:.1} V {bottom:.1} H {right:.1}\" fill=\"none\" \
+             stroke=\"black\"/>\n\
+             <text x=\"{label:.1}\" y=\"{top:.1}\" text-anchor=\"end\">{highest} m</text>\n\
+             <text x=\"{label:.1}\" y=\"{bottom:.1}\" text-anchor=\"end\">0 m</text>\n\
+             <text x=\"{right:.1}\" y=\"{under:.1}\" text-anchor=\"end\">{length:.0} m</text>\n",
+            left = x(0.0),
+            right = x(length),
+            top = y(highest),
+            bottom = y(0.0),
+            label = x(0.0) - 6.0,
+            under = y(0.0) + 18.0,
+            highest = highest,
+            length = along
+        );
+
+        let polyline: Vec<_> = points
+            .iter()
+            .map(|(along, altitude)| format!("{:.1},{:.1}", x(*along), y(*altitude)))
+            .collect();
+
+        svg += &format!(
+            "<polyline points=\"{}\" fill=\"none\" stroke=\"#1e64c8\" stroke-width=\"2\"/>\n",
+            polyline.join(" ")
+        );
+
+        for (index, (along, altitude)) in points.iter().enumerate() {
+            svg += &format!(
+                "<circle cx=\"{x:.1}\" cy=\"{y:.1}\" r=\"3\" fill=\"#1e64c8\"/>\n\
+                 <text x=\"{x:.1}\" y=\"{label:.1}\" text-anchor=\"middle\" \
+                 font-size=\"10\">{number}</text>\n",
+                x = x(*along),
+                y = y(*altitude),
+                label = y(*altitude) - 8.0,
+                number = index + 1
+            );
+        }
+
+        svg += "</svg>\n";
+
+        svg
+    }
+}
+
+fn actions(actions: &[Action]) -> String {
+    let actions: Vec<_> = actions.iter().map(|a| text(&a.to_string())).collect();
+
+    actions.join(", ")
+}
+
+fn duration(seconds: f64) -> String {
+    let seconds = seconds.round() as u64;
+
+    match (seconds / 3600, seconds / 60 % 60, seconds % 60) {
+        (0, 0, s) => format!("{} s", s),
+        (0, m, s) => format!("{} min {} s", m, s),
+        (h, m, s) => format!("{} h {} min {} s", h, m, s),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::flightplan::{Action, FlightPlan, Waypoint};
+
+    #[test]
+    fn report_needs_no_external_asset() {
+        let mut flightplan = FlightPlan::new("<ridge>", 45.0, 6.0);
+
+        // 100 m legs at 5 m/s, and a 10 s delay
+        flightplan.plan.waypoints = [(45.0, 6.0, None), (45.0009, 6.0, Some(10))]
+            .into_iter()
+            .map(|(latitude, longitude, delay)| {
+                Waypoint::at(latitude, longitude)
+                    .with_altitude(40)
+                    .with_actions(
+                        delay
+                            .map(|delay| Action::Delay { delay })
+                            .into_iter()
+                            .collect(),
+                    )
+            })
+            .collect();
+
+        let html = flightplan.to_html("ridge.csv", b"mission");
+
+        assert!(html.contains("<h1>&lt;ridge&gt;</h1>"));
+        assert!(html.contains("<td>30 s</td>"));
+        assert!(html.contains(
+            // sha256("mission")
+            "<code>ceb00a91525da64fb171b103ae30ef3b817fa3bc78ecde99b884eedbe9131003</code>"
+        ));
+        assert!(!html.contains("src=") && !html.contains("href="));
+    }
+}
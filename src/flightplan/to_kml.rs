@@ -0,0 +1,292 @@
+use std::collections::HashMap;
+
+use kml::{
+    types::{
+        AltitudeMode, Coord, Element, Geometry, Icon, IconStyle, LineString, LineStyle, Placemark,
+        Point, PolyStyle, Style,
+    },
+    Kml,
+};
+
+use super::FlightPlan;
+
+const PATH_STYLE: &str = "path";
+const WAYPOINT_STYLE: &str = "waypoint";
+const LINE_OF_SIGHT_STYLE: &str = "line-of-sight";
+
+const WAYPOINT_ICON: &str = "http://maps.google.com/mapfiles/kml/paddle/wht-blank.png";
+const POI_ICON: &str = "http://maps.google.com/mapfiles/kml/shapes/target.png";
+
+/// A document with the plans' folders, after the styles they share.
+///
+/// FreeFlight altitudes are relative to the takeoff point: given the elevation of the
+/// takeoff point above sea level, altitudes are made absolute, otherwise they are
+/// shown relative to the ground below each point, as the document's description tells.
+pub(super) fn document(flightplans: &[FlightPlan], takeoff_elevation: Option<f64>) -> Kml {
+    let description = match takeoff_elevation {
+        Some(elevation) => format!("Altitudes above sea level, taking off at {} m.", elevation),
+        None => "Altitudes relative to the ground below each point, \
+            the takeoff elevation being unknown."
+            .to_owned(),
+    };
+
+    let mut elements = vec![
+        text_element("description", &description),
+        style(PATH_STYLE, None, Some(("ff00c0ff", 3.0))),
+        style(WAYPOINT_STYLE, Some((WAYPOINT_ICON, "ffffffff")), None),
+        style(LINE_OF_SIGHT_STYLE, None, Some(("80ffffff", 1.0))),
+    ];
+
+    let mut colors: Vec<i32> = flightplans
+        .iter()
+        .flat_map(|flightplan| &flightplan.plan.poi)
+        .map(|poi| poi.color)
+        .collect();
+
+    colors.sort_unstable();
+    colors.dedup();
+
+    elements.extend(
+        colors
+            .into_iter()
+            .map(|color| style(&poi_style(color), Some((POI_ICON, &kml_color(color))), None)),
+    );
+
+    elements.extend(
+        flightplans
+            .iter()
+            .map(|flightplan| flightplan.to_kml(takeoff_elevation)),
+    );
+
+    Kml::Document {
+        attrs: HashMap::new(),
+        elements,
+    }
+}
+
+impl<'f> FlightPlan<'f> {
+    /// A folder with the plan's path, waypoints, points of interest and lines of sight,
+    /// using the styles declared by the document it goes in.
+    pub fn to_kml(&self, takeoff_elevation: Option<f64>) -> Kml {
+        let (altitude_mode, offset) = match takeoff_elevation {
+            Some(elevation) => (AltitudeMode::Absolute, elevation),
+            None => (AltitudeMode::RelativeToGround, 0.0),
+        };
+
+        let coord = |latitude: f64, longitude: f64, altitude: f64| {
+            Coord::new(longitude, latitude, Some(altitude + offset))
+        };
+
+        let mut elements = vec![text_element("name", &self.title)];
+
+        let waypoints = &self.plan.waypoints;
+
+        elements.push(placemark(
+            &self.title,
+            None,
+            PATH_STYLE,
+            Geometry::LineString(LineString {
+                coords: waypoints
+                    .iter()
+                    .map(|w| coord(w.latitude, w.longitude, w.altitude as f64))
+                    .collect(),
+                extrude: true,
+                altitude_mode,
+                ..Default::default()
+            }),
+        ));
+
+        for (index, waypoint) in waypoints.iter().enumerate() {
+            let position = coord(
+                waypoint.latitude,
+                waypoint.longitude,
+                waypoint.altitude as f64,
+            );
+
+            let actions: Vec<_> = waypoint
+                .actions
+                .iter()
+                .flatten()
+                .map(ToString::to_string)
+                .collect();
+
+            let description = match actions.as_slice() {
+                [] => None,
+                actions => Some(actions.join("\n")),
+            };
+
+            elements.push(placemark(
+                &(index + 1).to_string(),
+                description,
+                WAYPOINT_STYLE,
+                Geometry::Point(Point {
+                    coord: position,
+                    altitude_mode,
+                    ..Default::default()
+                }),
+            ));
+
+            // Numbered from 1, as in the other exports.
+            let poi = waypoint.poi.and_then(|poi_index| {
+                let poi = self.plan.poi.get(poi_index as usize)?;
+
+                Some((usize::from(poi_index) + 1, poi))
+            });
+
+            if let Some((number, poi)) = poi {
+                elements.push(placemark(
+                    &format!("{} → POI {}", index + 1, number),
+                    None,
+                    LINE_OF_SIGHT_STYLE,
+                    Geometry::LineString(LineString {
+                        coords: vec![
+                            position,
+                            coord(poi.latitude, poi.longitude, poi.altitude as f64),
+                        ],
+                        altitude_mode,
+                        ..Default::default()
+                    }),
+                ));
+            }
+        }
+
+        for (index, poi) in self.plan.poi.iter().enumerate() {
+            elements.push(placemark(
+                &format!("POI {}", index + 1),
+                None,
+                &poi_style(poi.color),
+                Geometry::Point(Point {
+                    coord: coord(poi.latitude, poi.longitude, poi.altitude as f64),
+                    altitude_mode,
+                    ..Default::default()
+                }),
+            ));
+        }
+
+        Kml::Folder {
+            attrs: HashMap::new(),
+            elements,
+        }
+    }
+}
+
+/// FreeFlight colors are ARGB, KML ones are `aabbggrr`.
+fn kml_color(argb: i32) -> String {
+    let [a, r, g, b] = argb.to_be_bytes();
+
+    format!("{:02x}{:02x}{:02x}{:02x}", a, b, g, r)
+}
+
+fn poi_style(color: i32) -> String {
+    format!("poi-{:08x}", color)
+}
+
+/// `icon` is an icon's URL and its color, `line` a line's color and width.
+fn style(id: &str, icon: Option<(&str, &str)>, line: Option<(&str, f64)>) -> Kml {
+    Kml::Style(Style {
+        id: id.to_owned(),
+        icon: icon.map(|(href, color)| IconStyle {
+            icon: Icon {
+                href: href.to_owned(),
+            },
+            color: color.to_owned(),
+            ..Default::default()
+        }),
+        line: line.map(|(color, width)| LineStyle {
+            color: color.to_owned(),
+            width,
+            ..Default::default()
+        }),
+        // Walls below extruded lines
+        poly: line.map(|_| PolyStyle {
+            color: "40ffffff".to_owned(),
+            ..Default::default()
+        }),
+        ..Default::default()
+    })
+}
+
+fn placemark(name: &str, description: Option<String>, style: &str, geometry: Geometry) -> Kml {
+    Kml::Placemark(Placemark {
+        name: Some(name.to_owned()),
+        description,
+        geometry: Some(geometry),
+        children: vec![Element {
+            name: "styleUrl".to_owned(),
+            content: Some(format!("#{}", style)),
+            ..Default::default()
+        }],
+        ..Default::default()
+    })
+}
+
+fn text_element(name: &str, content: &str) -> Kml {
+    Kml::Element(Element {
+        name: name.to_owned(),
+        content: Some(content.to_owned()),
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use kml::Kml;
+
+    use super::{document, kml_color};
+    use crate::flightplan::{FlightPlan, PointOfInterest, Waypoint};
+
+    #[test]
+    fn colors_are_swapped_to_kml_order() {
+        // Opaque brown, as ARGB 0xff8c3e2c
+        assert_eq!(kml_color(0xff8c3e2cu32 as i32), "ff2c3e8c");
+    }
+
+    #[test]
+    fn styles_are_declared_once_per_document() {
+        let flightplans: Vec<_> = ["first", "second"]
+            .into_iter()
+            .map(|title| {
+                let mut flightplan = FlightPlan::new(title, 48.8583, 2.2945);
+
+                flightplan.plan.waypoints = vec![Waypoint::at(48.8583, 2.2945).with_poi(Some(0))];
+                flightplan.plan.poi = vec![PointOfInterest {
+                    latitude: 48.8584,
+                    longitude: 2.2946,
+                    altitude: 0,
+                    color: 0xff8c3e2cu32 as i32,
+                }];
+
+                flightplan
+            })
+            .collect();
+
+        let elements = match document(&flightplans, None) {
+            Kml::Document { elements, .. } => elements,
+            other => panic!("not a document: {:?}", other),
+        };
+
+        let styles: Vec<_> = elements
+            .iter()
+            .filter_map(|element| match element {
+                Kml::Style(style) => Some(style.id.as_str()),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(
+            styles,
+            ["path", "waypoint", "line-of-sight", "poi-ff8c3e2c"]
+        );
+
+        for element in &elements {
+            if let Kml::Folder { elements, .. } = element {
+                assert!(!elements.iter().any(|e| matches!(e, Kml::Style(_))));
+            }
+        }
+
+        assert!(matches!(
+            &elements[0],
+            Kml::Element(e) if e.content.as_deref().unwrap_or_default().contains("relative")
+        ));
+    }
+}
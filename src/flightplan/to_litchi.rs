@@ -0,0 +1,137 @@
+use crate::litchi::{
+    csv::de::{Altitude, Coordinates, GimbalSettings, MissionRecord},
+    Action as LitchiAction,
+};
+
+use super::{Action, FlightPlan, Waypoint};
+
+impl<'f> From<&'_ FlightPlan<'f>> for Vec<MissionRecord> {
+    fn from(flightplan: &FlightPlan<'f>) -> Self {
+        let mut records: Vec<MissionRecord> = flightplan
+            .plan
+            .waypoints
+            .iter()
+            .map(|waypoint| MissionRecord::from((flightplan, waypoint)))
+            .collect();
+
+        // Litchi has no takeoff sequence: it is done at the first waypoint instead.
+        if let Some(first) = records.first_mut() {
+            let takeoff = flightplan.plan.takeoff.iter().filter_map(Option::from);
+
+            first.actions.splice(0..0, takeoff);
+        }
+
+        records
+    }
+}
+
+impl<'f> From<(&'_ FlightPlan<'f>, &'_ Waypoint)> for MissionRecord {
+    /// FreeFlight altitudes are relative to takeoff, Litchi's `Absolute` mode, not above ground.
+    fn from((flightplan, waypoint): (&FlightPlan<'f>, &Waypoint)) -> Self {
+        let poi = waypoint
+            .poi
+            .and_then(|index| flightplan.plan.poi.get(index as usize))
+            .map(|poi| Coordinates {
+                latitude: poi.latitude,
+                longitude: poi.longitude,
+                altitude: Altitude::Absolute(poi.altitude.max(0) as u16),
+            });
+
+        let gimbal = poi.as_ref().map(|_| GimbalSettings::FocusPoi(0.0));
+
+        let actions = waypoint
+            .actions
+            .iter()
+            .flatten()
+            .filter_map(Option::from)
+            .collect();
+
+        MissionRecord {
+            waypoint: Coordinates {
+                latitude: waypoint.latitude,
+                longitude: waypoint.longitude,
+                altitude: Altitude::Absolute(waypoint.altitude),
+            },
+            heading: (360.0 - waypoint.yaw).rem_euclid(360.0),
+            curvesize: 0.0,
+            rotationdir: 0,
+            gimbal,
+            speed: waypoint.speed as f32,
+            poi,
+            photo_timeinterval: -1,
+            photo_distinterval: -1,
+            actions,
+        }
+    }
+}
+
+/// Litchi has no equivalent for stopping pictures or landing.
+impl From<&'_ Action> for Option<LitchiAction> {
+    fn from(action: &'_ Action) -> Self {
+        use LitchiAction::*;
+
+        match action {
+            Action::Delay { delay } => Some(StayFor { ms: delay * 1000 }),
+
+            Action::ImageStartCapture { .. } => Some(TakePhoto),
+
+            Action::VideoStartCapture { .. } => Some(StartRecording),
+
+            Action::VideoStopCapture => Some(StopRecording),
+
+            Action::Panorama { angle, .. } => Some(RotateAircraft {
+                angle: (*angle as i16).rem_euclid(360) as u16,
+            }),
+
+            Action::Tilt { angle, .. } => Some(TiltCamera {
+                angle: *angle as i16,
+            }),
+
+            Action::ImageStopCapture | Action::Landing => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        flightplan::{FlightPlan, PointOfInterest, Waypoint},
+        litchi::csv::{ser, MissionRecord},
+    };
+
+    #[test]
+    fn altitudes_are_relative_to_takeoff() {
+        let mut flightplan = FlightPlan::new("test", 48.8583, 2.2945);
+
+        flightplan.plan.poi = vec![PointOfInterest {
+            latitude: 48.8584,
+            longitude: 2.2946,
+            altitude: 12,
+            color: 0,
+        }];
+
+        flightplan.plan.waypoints = vec![Waypoint::at(48.8583, 2.2945).with_poi(Some(0))];
+
+        let records = Vec::<MissionRecord>::from(&flightplan);
+
+        let mut csv = vec![];
+
+        ser::to_writer(&records, &mut csv).unwrap();
+
+        let mut reader = csv::Reader::from_reader(csv.as_slice());
+
+        let headers = reader.headers().unwrap().clone();
+        let record = reader.records().next().unwrap().unwrap();
+
+        let field = |name: &str| {
+            let index = headers.iter().position(|h| h == name).unwrap();
+
+            record[index].to_owned()
+        };
+
+        assert_eq!(field("altitude(m)"), "30");
+        assert_eq!(field("altitudemode"), "0");
+        assert_eq!(field("poi_altitude(m)"), "12");
+        assert_eq!(field("poi_altitudemode"), "0");
+    }
+}
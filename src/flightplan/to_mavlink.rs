@@ -0,0 +1,249 @@
+use crate::mavlink::{self, command::*, MissionItem};
+
+use super::{Action, FlightPlan, Waypoint};
+
+/// Distance in meters at which a waypoint counts as reached.
+const ACCEPTANCE_RADIUS_M: f64 = 1.0;
+
+/// What sets the missions of an autopilot apart, along the items shared by all of them.
+pub(super) trait Flavor {
+    /// Yaw of the takeoff item, NaN to keep the current one
+    const TAKEOFF_YAW: f64;
+
+    /// Seconds to hold at `waypoint`, and heading to reach it with, NaN to keep the current one.
+    fn hold_and_heading(&self, waypoint: &Waypoint) -> (f64, f64);
+
+    /// Item turning the aircraft once `waypoint` is reached, if any.
+    /// `poi_changed` when it looks at another point of interest than the previous waypoint.
+    fn yaw(&mut self, _waypoint: &Waypoint, _poi_changed: bool) -> Option<MissionItem> {
+        None
+    }
+
+    /// Item of an action, `waypoint` being where it happens, `None` for takeoff actions.
+    fn action(&self, action: &Action, waypoint: Option<&Waypoint>) -> Option<MissionItem>;
+}
+
+/// Items in the order MAVLink autopilots run them: takeoff, takeoff actions,
+/// then each waypoint preceded by its speed and point of interest when they change,
+/// and followed by its actions.
+pub(super) fn items<F: Flavor>(flightplan: &FlightPlan, flavor: &mut F) -> Vec<MissionItem> {
+    let mut items = vec![];
+
+    if let Some(first) = flightplan.plan.waypoints.first() {
+        items.push(MissionItem::at(
+            NAV_TAKEOFF,
+            [0.0, 0.0, 0.0, F::TAKEOFF_YAW],
+            first.latitude,
+            first.longitude,
+            first.altitude as f64,
+        ));
+    }
+
+    items.extend(
+        flightplan
+            .plan
+            .takeoff
+            .iter()
+            .filter_map(|action| flavor.action(action, None)),
+    );
+
+    let mut speed = None;
+    let mut poi = None;
+
+    for waypoint in &flightplan.plan.waypoints {
+        if speed != Some(waypoint.speed) {
+            speed = Some(waypoint.speed);
+
+            items.push(MissionItem::command(
+                DO_CHANGE_SPEED,
+                [1.0, waypoint.speed as f64, -1.0, 0.0],
+            ));
+        }
+
+        let poi_changed = poi != waypoint.poi;
+
+        if poi_changed {
+            poi = waypoint.poi;
+
+            items.push(roi(flightplan, waypoint.poi));
+        }
+
+        let (hold, heading) = flavor.hold_and_heading(waypoint);
+
+        items.push(MissionItem::at(
+            NAV_WAYPOINT,
+            [hold, ACCEPTANCE_RADIUS_M, 0.0, heading],
+            waypoint.latitude,
+            waypoint.longitude,
+            waypoint.altitude as f64,
+        ));
+
+        items.extend(flavor.yaw(waypoint, poi_changed));
+
+        let actions = waypoint.actions.iter().flatten();
+
+        items.extend(actions.filter_map(|action| flavor.action(action, Some(waypoint))));
+    }
+
+    items
+}
+
+/// Missions of Parrot drones, running actions as FreeFlight does.
+struct Parrot;
+
+impl Flavor for Parrot {
+    const TAKEOFF_YAW: f64 = 0.0;
+
+    fn hold_and_heading(&self, waypoint: &Waypoint) -> (f64, f64) {
+        (0.0, (360.0 - waypoint.yaw).rem_euclid(360.0))
+    }
+
+    fn action(&self, action: &Action, waypoint: Option<&Waypoint>) -> Option<MissionItem> {
+        Some(MissionItem::from((action, waypoint)))
+    }
+}
+
+impl<'f> From<&'_ FlightPlan<'f>> for Vec<MissionItem> {
+    fn from(flightplan: &FlightPlan<'f>) -> Self {
+        items(flightplan, &mut Parrot)
+    }
+}
+
+/// Looks at the given point of interest, or stops looking at any.
+fn roi(flightplan: &FlightPlan, poi: Option<u8>) -> MissionItem {
+    match poi.and_then(|index| flightplan.plan.poi.get(index as usize)) {
+        Some(poi) => MissionItem::at(
+            DO_SET_ROI,
+            [mavlink::ROI_LOCATION, 0.0, 0.0, 0.0],
+            poi.latitude,
+            poi.longitude,
+            poi.altitude as f64,
+        ),
+
+        None => MissionItem::command(DO_SET_ROI, [mavlink::ROI_NONE, 0.0, 0.0, 0.0]),
+    }
+}
+
+/// `waypoint` is where the action happens, `None` for takeoff actions.
+impl From<(&'_ Action, Option<&'_ Waypoint>)> for MissionItem {
+    fn from((action, waypoint): (&Action, Option<&Waypoint>)) -> Self {
+        match *action {
+            Action::VideoStartCapture {
+                camera_id,
+                resolution,
+                fps,
+            } => MissionItem::command(
+                VIDEO_START_CAPTURE,
+                [camera_id as f64, fps as f64, resolution as f64, 0.0],
+            ),
+
+            Action::VideoStopCapture => MissionItem::command(VIDEO_STOP_CAPTURE, [0.0; 4]),
+
+            Action::ImageStartCapture {
+                period,
+                resolution,
+                nb_of_pictures,
+            } => MissionItem::command(
+                IMAGE_START_CAPTURE,
+                [period as f64, nb_of_pictures as f64, resolution, 0.0],
+            ),
+
+            Action::ImageStopCapture => MissionItem::command(IMAGE_STOP_CAPTURE, [0.0; 4]),
+
+            Action::Delay { delay } => {
+                MissionItem::command(NAV_DELAY, [delay as f64, 0.0, 0.0, 0.0])
+            }
+
+            Action::Tilt { angle, .. } => MissionItem {
+                z: mavlink::MOUNT_MODE_MAVLINK_TARGETING,
+                ..MissionItem::command(DO_MOUNT_CONTROL, [angle as f64, 0.0, 0.0, 0.0])
+            },
+
+            // FreeFlight's angles grow counterclockwise, like its yaw.
+            Action::Panorama { angle, speed } => MissionItem::command(
+                CONDITION_YAW,
+                [
+                    (angle as f64).abs(),
+                    speed as f64,
+                    if angle < 0 { 1.0 } else { -1.0 },
+                    1.0,
+                ],
+            ),
+
+            Action::Landing => match waypoint {
+                Some(waypoint) => MissionItem::at(
+                    NAV_LAND,
+                    [0.0; 4],
+                    waypoint.latitude,
+                    waypoint.longitude,
+                    0.0,
+                ),
+
+                None => MissionItem::command(NAV_LAND, [0.0; 4]),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        flightplan::{Action, FlightPlan, PointOfInterest, Waypoint},
+        mavlink::{command::*, MissionItem},
+    };
+
+    fn waypoint(speed: u8, poi: Option<u8>, actions: Vec<Action>) -> Waypoint {
+        Waypoint::at(48.8583, 2.2945)
+            .with_yaw(90.0)
+            .with_speed(speed)
+            .with_poi(poi)
+            .with_actions(actions)
+    }
+
+    #[test]
+    fn speed_and_poi_are_only_set_when_changing() {
+        let mut flightplan = FlightPlan::new("test", 48.8583, 2.2945);
+
+        flightplan.plan.takeoff = vec![Action::Tilt {
+            angle: -30,
+            speed: 10,
+        }];
+
+        flightplan.plan.poi = vec![PointOfInterest {
+            latitude: 48.8584,
+            longitude: 2.2946,
+            altitude: 0,
+            color: 0,
+        }];
+
+        flightplan.plan.waypoints = vec![
+            waypoint(5, Some(0), vec![]),
+            waypoint(5, Some(0), vec![Action::Delay { delay: 3 }]),
+            waypoint(8, None, vec![Action::Landing]),
+        ];
+
+        let items: Vec<MissionItem> = (&flightplan).into();
+
+        let commands: Vec<_> = items.iter().map(|item| item.command).collect();
+
+        assert_eq!(
+            commands,
+            [
+                NAV_TAKEOFF,
+                DO_MOUNT_CONTROL,
+                DO_CHANGE_SPEED,
+                DO_SET_ROI,
+                NAV_WAYPOINT,
+                NAV_WAYPOINT,
+                NAV_DELAY,
+                DO_CHANGE_SPEED,
+                DO_SET_ROI,
+                NAV_WAYPOINT,
+                NAV_LAND
+            ]
+        );
+
+        // Yaw is turned into a clockwise heading.
+        assert_eq!(items[4].params[3], 270.0);
+    }
+}
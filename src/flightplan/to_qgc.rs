@@ -0,0 +1,240 @@
+use crate::mavlink::{command::*, qgc, MissionItem};
+
+use super::{
+    to_mavlink::{self, Flavor},
+    Action, FlightPlan, Waypoint,
+};
+
+impl<'f> FlightPlan<'f> {
+    /// A QGroundControl plan, for ArduPilot and PX4 vehicles.
+    ///
+    /// Unlike the missions sent to Parrot drones, delays become the hold time of their
+    /// waypoint, yaw is set by `CONDITION_YAW` when not looking at a point of interest,
+    /// and captures use the parameters of the MAVLink specification.
+    /// The home position is the first waypoint, at `takeoff_elevation` above sea level.
+    pub fn to_qgc_plan(&self, takeoff_elevation: f64) -> qgc::Plan {
+        let items = to_mavlink::items(self, &mut Qgc { heading: None });
+
+        let hover_speed = self
+            .plan
+            .waypoints
+            .first()
+            .map_or(super::DEFAULT_SPEED_MS, |w| w.speed) as f64;
+
+        qgc::Plan {
+            items,
+            home: self.home(takeoff_elevation),
+            cruise_speed: hover_speed,
+            hover_speed,
+        }
+    }
+
+    /// Items of a Mission Planner waypoint file: the home position above sea level,
+    /// then the items of the QGroundControl plan.
+    pub fn to_waypoints(&self, takeoff_elevation: f64) -> Vec<MissionItem> {
+        let [latitude, longitude, altitude] = self.home(takeoff_elevation);
+
+        let mut items = vec![MissionItem::home(latitude, longitude, altitude)];
+
+        items.append(&mut self.to_qgc_plan(takeoff_elevation).items);
+
+        items
+    }
+
+    /// The first waypoint, at `takeoff_elevation` above sea level.
+    fn home(&self, takeoff_elevation: f64) -> [f64; 3] {
+        match self.plan.waypoints.first() {
+            Some(first) => [first.latitude, first.longitude, takeoff_elevation],
+            None => [self.latitude, self.longitude, takeoff_elevation],
+        }
+    }
+}
+
+/// Missions of ArduPilot and PX4 vehicles.
+struct Qgc {
+    /// Heading last set by `CONDITION_YAW`, forgotten when looking at another point of interest
+    heading: Option<f64>,
+}
+
+impl Flavor for Qgc {
+    const TAKEOFF_YAW: f64 = f64::NAN;
+
+    fn hold_and_heading(&self, waypoint: &Waypoint) -> (f64, f64) {
+        let hold: usize = waypoint
+            .actions
+            .iter()
+            .flatten()
+            .map(|action| match action {
+                Action::Delay { delay } => *delay,
+                _ => 0,
+            })
+            .sum();
+
+        (hold as f64, f64::NAN)
+    }
+
+    fn yaw(&mut self, waypoint: &Waypoint, poi_changed: bool) -> Option<MissionItem> {
+        if poi_changed {
+            self.heading = None;
+        }
+
+        // FreeFlight's yaw grows counterclockwise, headings clockwise.
+        let yaw = (360.0 - waypoint.yaw).rem_euclid(360.0);
+
+        if waypoint.poi.is_some() || self.heading == Some(yaw) {
+            return None;
+        }
+
+        self.heading = Some(yaw);
+
+        Some(MissionItem::command(CONDITION_YAW, [yaw, 0.0, 0.0, 0.0]))
+    }
+
+    /// Delays are held at their waypoint instead.
+    fn action(&self, action: &Action, waypoint: Option<&Waypoint>) -> Option<MissionItem> {
+        let item = match *action {
+            Action::Delay { .. } if waypoint.is_some() => return None,
+
+            // Stream 0 is every camera's.
+            Action::VideoStartCapture { .. } => {
+                MissionItem::command(VIDEO_START_CAPTURE, [0.0, 0.0, f64::NAN, f64::NAN])
+            }
+
+            // A picture every `period` seconds until stopped, or a single one.
+            Action::ImageStartCapture { period, .. } => MissionItem::command(
+                IMAGE_START_CAPTURE,
+                [
+                    0.0,
+                    period as f64,
+                    if period > 0 { 0.0 } else { 1.0 },
+                    f64::NAN,
+                ],
+            ),
+
+            _ => MissionItem::from((action, waypoint)),
+        };
+
+        Some(item)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        flightplan::{Action, FlightPlan, PointOfInterest, Waypoint},
+        mavlink::{self, command::*},
+    };
+
+    #[test]
+    fn delays_are_held_and_yaw_is_set() {
+        let mut flightplan = FlightPlan::new("test", 48.8583, 2.2945);
+
+        flightplan.plan.waypoints = [90.0, 90.0]
+            .into_iter()
+            .map(|yaw| {
+                Waypoint::at(48.8583, 2.2945)
+                    .with_yaw(yaw)
+                    .with_actions(vec![Action::Delay { delay: 4 }])
+            })
+            .collect();
+
+        let plan = flightplan.to_qgc_plan(100.0);
+
+        let commands: Vec<_> = plan.items.iter().map(|item| item.command).collect();
+
+        assert_eq!(
+            commands,
+            [
+                NAV_TAKEOFF,
+                DO_CHANGE_SPEED,
+                NAV_WAYPOINT,
+                CONDITION_YAW,
+                NAV_WAYPOINT
+            ]
+        );
+
+        // Held, then turned once only.
+        assert_eq!(plan.items[2].params[0], 4.0);
+        assert_eq!(plan.items[3].params[0], 270.0);
+        assert_eq!(plan.home[2], 100.0);
+    }
+
+    #[test]
+    fn tilt_poi_and_captures_are_set() {
+        let mut flightplan = FlightPlan::new("test", 48.8583, 2.2945);
+
+        flightplan.plan.takeoff = vec![Action::Tilt {
+            angle: -30,
+            speed: 10,
+        }];
+
+        flightplan.plan.poi = vec![PointOfInterest {
+            latitude: 48.8584,
+            longitude: 2.2946,
+            altitude: 12,
+            color: 0,
+        }];
+
+        flightplan.plan.waypoints = vec![
+            Waypoint::at(48.8583, 2.2945)
+                .with_poi(Some(0))
+                .with_actions(vec![Action::ImageStartCapture {
+                    period: 2,
+                    resolution: 14.0,
+                    nb_of_pictures: 0,
+                }]),
+            Waypoint::at(48.8583, 2.2945)
+                .with_poi(Some(0))
+                .with_actions(vec![
+                    Action::ImageStopCapture,
+                    Action::ImageStartCapture {
+                        period: 0,
+                        resolution: 14.0,
+                        nb_of_pictures: 1,
+                    },
+                    Action::VideoStartCapture {
+                        camera_id: 0,
+                        resolution: 2073600,
+                        fps: 30,
+                    },
+                ]),
+        ];
+
+        let items = flightplan.to_qgc_plan(100.0).items;
+
+        let commands: Vec<_> = items.iter().map(|item| item.command).collect();
+
+        assert_eq!(
+            commands,
+            [
+                NAV_TAKEOFF,
+                DO_MOUNT_CONTROL,
+                DO_CHANGE_SPEED,
+                DO_SET_ROI,
+                NAV_WAYPOINT,
+                IMAGE_START_CAPTURE,
+                NAV_WAYPOINT,
+                IMAGE_STOP_CAPTURE,
+                IMAGE_START_CAPTURE,
+                VIDEO_START_CAPTURE
+            ]
+        );
+
+        assert_eq!(items[1].params[0], -30.0);
+        assert_eq!(items[1].z, mavlink::MOUNT_MODE_MAVLINK_TARGETING);
+
+        assert_eq!(items[3].params[0], mavlink::ROI_LOCATION);
+        assert_eq!(
+            [items[3].x, items[3].y, items[3].z],
+            [48.8584, 2.2946, 12.0]
+        );
+
+        // A picture every 2 seconds until stopped, then a single one.
+        assert_eq!(items[5].params[..3], [0.0, 2.0, 0.0]);
+        assert_eq!(items[8].params[..3], [0.0, 0.0, 1.0]);
+        assert!(items[8].params[3].is_nan());
+
+        assert_eq!(items[9].params[..2], [0.0, 0.0]);
+        assert!(items[9].params[2..].iter().all(|param| param.is_nan()));
+    }
+}
@@ -0,0 +1,350 @@
+use std::f64::consts::PI;
+
+use quick_xml::escape::escape;
+
+use super::{geo::project, FlightPlan};
+
+/// Width, or height, of the drawing's longest side
+const SIZE: f64 = 800.0;
+
+/// Room around the plan for the labels, the scale bar and the north arrow
+const MARGIN: f64 = 60.0;
+
+/// Extent in meters under which plans are not enlarged further
+const MIN_EXTENT_M: f64 = 20.0;
+
+/// Shortest side of the drawing, for narrow plans to leave room for the scale bar
+const MIN_SIDE: f64 = SIZE / 2.0;
+
+const PATH_COLOR: &str = "#1e64c8";
+
+/// Length of the yaw ticks
+const TICK: f64 = 22.0;
+
+impl<'f> FlightPlan<'f> {
+    /// A plan view of the waypoints and points of interest, north up, without any basemap.
+    ///
+    /// The path has an arrow in the middle of each leg, waypoints are numbered from 1
+    /// with a tick showing their yaw, and points of interest are drawn in their color,
+    /// with dashed lines from the waypoints looking at them.
+    pub fn to_svg(&self) -> String {
+        let plane = Plane::new(self);
+
+        let (width, height) = plane.size();
+
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w:.0}\" height=\"{h:.0}\" \
+             viewBox=\"0 0 {w:.0} {h:.0}\" font-family=\"sans-serif\" font-size=\"12\">\n",
+            w = width,
+            h = height
+        );
+
+        svg += &format!("<title>{}</title>\n", text(&self.title));
+        svg += "<rect width=\"100%\" height=\"100%\" fill=\"white\"/>\n";
+
+        let waypoints = &self.plan.waypoints;
+
+        let points: Vec<_> = waypoints
+            .iter()
+            .map(|w| plane.point(w.latitude, w.longitude))
+            .collect();
+
+        let pois: Vec<_> = self
+            .plan
+            .poi
+            .iter()
+            .map(|poi| plane.point(poi.latitude, poi.longitude))
+            .collect();
+
+        for (waypoint, (x, y)) in waypoints.iter().zip(&points) {
+            let index = waypoint.poi.map(usize::from);
+
+            let poi = index.and_then(|i| pois.get(i).zip(self.plan.poi.get(i)));
+
+            if let Some(((px, py), poi)) = poi {
+                svg += &format!(
+                    "<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"{}\" \
+                     stroke-width=\"1\" stroke-dasharray=\"5 4\"/>\n",
+                    x,
+                    y,
+                    px,
+                    py,
+                    css_color(poi.color)
+                );
+            }
+        }
+
+        let polyline: Vec<_> = points
+            .iter()
+            .map(|(x, y)| format!("{:.1},{:.1}", x, y))
+            .collect();
+
+        svg += &format!(
+            "<polyline points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"2\"/>\n",
+            polyline.join(" "),
+            PATH_COLOR
+        );
+
+        for pair in points.windows(2) {
+            let ((xa, ya), (xb, yb)) = (pair[0], pair[1]);
+
+            if (xb - xa).hypot(yb - ya) < 2.0 * TICK {
+                continue;
+            }
+
+            let angle = (yb - ya).atan2(xb - xa) * 180.0 / PI;
+
+            svg += &format!(
+                "<path d=\"M -6 -5 L 6 0 L -6 5 z\" fill=\"{}\" \
+                 transform=\"translate({:.1} {:.1}) rotate({:.1})\"/>\n",
+                PATH_COLOR,
+                (xa + xb) / 2.0,
+                (ya + yb) / 2.0,
+                angle
+            );
+        }
+
+        for (index, (poi, (x, y))) in self.plan.poi.iter().zip(&pois).enumerate() {
+            svg += &format!(
+                "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"7\" fill=\"{}\" stroke=\"black\"/>\n",
+                x,
+                y,
+                css_color(poi.color)
+            );
+
+            svg += &format!(
+                "<text x=\"{:.1}\" y=\"{:.1}\">POI {}</text>\n",
+                x + 10.0,
+                y - 10.0,
+                index + 1
+            );
+        }
+
+        for (index, (waypoint, (x, y))) in waypoints.iter().zip(&points).enumerate() {
+            // FreeFlight's yaw grows counterclockwise, headings clockwise.
+            let heading = (360.0 - waypoint.yaw).rem_euclid(360.0).to_radians();
+
+            svg += &format!(
+                "<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"black\" \
+                 stroke-width=\"2\"/>\n",
+                x,
+                y,
+                x + TICK * heading.sin(),
+                y - TICK * heading.cos()
+            );
+
+            svg += &format!(
+                "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"10\" fill=\"white\" stroke=\"{}\" \
+                 stroke-width=\"2\"/>\n",
+                x, y, PATH_COLOR
+            );
+
+            svg += &format!(
+                "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\" \
+                 dominant-baseline=\"central\" font-size=\"10\">{}</text>\n",
+                x,
+                y,
+                index + 1
+            );
+        }
+
+        svg += &plane.scale_bar(height);
+        svg += &north_arrow(width);
+
+        svg += "</svg>\n";
+
+        svg
+    }
+}
+
+/// Projection of the plan on its drawing.
+struct Plane {
+    origin: (f64, f64),
+
+    /// Meters east and north of the origin, at the bottom left and top right corners
+    min: (f64, f64),
+    max: (f64, f64),
+
+    /// Pixels per meter
+    scale: f64,
+}
+
+impl Plane {
+    fn new(flightplan: &FlightPlan) -> Self {
+        let origin = match flightplan.plan.waypoints.first() {
+            Some(first) => (first.latitude, first.longitude),
+            None => (flightplan.latitude, flightplan.longitude),
+        };
+
+        let coordinates = flightplan
+            .plan
+            .waypoints
+            .iter()
+            .map(|w| (w.latitude, w.longitude))
+            .chain(
+                flightplan
+                    .plan
+                    .poi
+                    .iter()
+                    .map(|p| (p.latitude, p.longitude)),
+            );
+
+        let (mut min, mut max) = ((0.0f64, 0.0f64), (0.0f64, 0.0f64));
+
+        for (latitude, longitude) in coordinates {
+            let (east, north) = project(latitude, longitude, origin.0, origin.1);
+
+            min = (min.0.min(east), min.1.min(north));
+            max = (max.0.max(east), max.1.max(north));
+        }
+
+        let extent = (max.0 - min.0).max(max.1 - min.1).max(MIN_EXTENT_M);
+
+        Plane {
+            origin,
+            min,
+            max,
+            scale: (SIZE - 2.0 * MARGIN) / extent,
+        }
+    }
+
+    fn size(&self) -> (f64, f64) {
+        (
+            ((self.max.0 - self.min.0) * self.scale + 2.0 * MARGIN).max(MIN_SIDE),
+            ((self.max.1 - self.min.1) * self.scale + 2.0 * MARGIN).max(MIN_SIDE),
+        )
+    }
+
+    /// Position on the drawing, whose y axis goes down, the plan being centered.
+    fn point(&self, latitude: f64, longitude: f64) -> (f64, f64) {
+        let (east, north) = project(latitude, longitude, self.origin.0, self.origin.1);
+
+        let (width, height) = self.size();
+
+        let left = (width - (self.max.0 - self.min.0) * self.scale) / 2.0;
+        let top = (height - (self.max.1 - self.min.1) * self.scale) / 2.0;
+
+        (
+            left + (east - self.min.0) * self.scale,
+            top + (self.max.1 - north) * self.scale,
+        )
+    }
+
+    /// A bar of 1, 2 or 5 times a power of ten meters, at the bottom left.
+    fn scale_bar(&self, height: f64) -> String {
+        let target = (SIZE - 2.0 * MARGIN) / 4.0 / self.scale;
+
+        let power = 10f64.powf(target.log10().floor());
+
+        let meters = [5.0, 2.0, 1.0]
+            .into_iter()
+            .map(|step| step * power)
+            .find(|meters| *meters <= target)
+            .unwrap_or(power);
+
+        let (x, y) = (MARGIN / 2.0, height - MARGIN / 3.0);
+        let length = meters * self.scale;
+
+        format!(
+            "<path d=\"M {x:.1} {top:.1} V {y:.1} H {end:.1} V {top:.1}\" fill=\"none\" \
+             stroke=\"black\" stroke-width=\"2\"/>\n\
+             <text x=\"{middle:.1}\" y=\"{label:.1}\" text-anchor=\"middle\">{meters} m</text>\n",
+            x = x,
+            y = y,
+            top = y - 6.0,
+            end = x + length,
+            middle = x + length / 2.0,
+            label = y - 10.0,
+            meters = meters
+        )
+    }
+}
+
+/// An arrow pointing up, at the top right.
+fn north_arrow(width: f64) -> String {
+    let (x, y) = (width - MARGIN / 2.0, MARGIN / 2.0);
+
+    format!(
+        "<path d=\"M {x:.1} {tip:.1} L {right:.1} {base:.1} L {x:.1} {notch:.1} L {left:.1} {base:.1} z\" \
+         fill=\"black\"/>\n\
+         <text x=\"{x:.1}\" y=\"{label:.1}\" text-anchor=\"middle\" font-weight=\"bold\">N</text>\n",
+        x = x,
+        tip = y - 14.0,
+        right = x + 8.0,
+        left = x - 8.0,
+        base = y + 10.0,
+        notch = y + 4.0,
+        label = y + 24.0
+    )
+}
+
+/// The color of a point of interest, without its alpha which is not always set.
+fn css_color(argb: i32) -> String {
+    let [_, r, g, b] = argb.to_be_bytes();
+
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+/// `text` escaped for XML and HTML.
+pub(super) fn text(text: &str) -> String {
+    String::from_utf8_lossy(&escape(text.as_bytes())).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::flightplan::{FlightPlan, PointOfInterest, Waypoint};
+
+    #[test]
+    fn waypoints_and_points_of_interest_are_drawn() {
+        let mut flightplan = FlightPlan::new("bridge & pylon", 48.8583, 2.2945);
+
+        flightplan.plan.poi = vec![PointOfInterest {
+            latitude: 48.8590,
+            longitude: 2.2950,
+            altitude: 2,
+            color: 0x32a852,
+        }];
+
+        flightplan.plan.waypoints = [(48.8583, 2.2945, None), (48.8583, 2.2960, Some(0))]
+            .into_iter()
+            .map(|(latitude, longitude, poi)| {
+                Waypoint::at(latitude, longitude)
+                    .with_yaw(90.0)
+                    .with_poi(poi)
+            })
+            .collect();
+
+        let svg = flightplan.to_svg();
+
+        assert!(svg.contains("<title>bridge &amp; pylon</title>"));
+        assert!(svg.contains("fill=\"#32a852\""));
+        assert_eq!(svg.matches("stroke-dasharray").count(), 1);
+        assert_eq!(svg.matches("r=\"10\"").count(), 2);
+
+        // Yaw 90° counterclockwise is west: ticks go left, staying level.
+        let ticks: Vec<_> = svg
+            .lines()
+            .filter(|line| line.starts_with("<line") && line.contains("stroke=\"black\""))
+            .map(|line| {
+                let attribute = |name: &str| -> f64 {
+                    let start = line.find(&format!(" {}=\"", name)).unwrap() + name.len() + 3;
+                    let end = start + line[start..].find('"').unwrap();
+
+                    line[start..end].parse().unwrap()
+                };
+
+                (
+                    attribute("x1"),
+                    attribute("y1"),
+                    attribute("x2"),
+                    attribute("y2"),
+                )
+            })
+            .collect();
+
+        assert_eq!(ticks.len(), 2);
+        assert!(ticks
+            .iter()
+            .all(|(x1, y1, x2, y2)| x2 < x1 && (y2 - y1).abs() < 0.1));
+    }
+}
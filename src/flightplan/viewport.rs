@@ -0,0 +1,208 @@
+use std::{f64::consts::PI, str::FromStr};
+
+use serde::{de, Deserialize, Deserializer};
+
+use super::FlightPlan;
+
+/// Closest zoom level used when everything fits in a few meters.
+const MAX_ZOOM: f64 = 19.0;
+
+/// Room left around the plan, as a factor of its extent.
+const MARGIN: f64 = 1.2;
+
+/// Visible map area, in 256 points wide web mercator tiles,
+/// for a tablet in landscape orientation.
+const SCREEN_WIDTH_TILES: f64 = 4.0;
+const SCREEN_HEIGHT_TILES: f64 = 3.0;
+
+/// Overrides of the computed map viewport.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct Viewport {
+    pub zoom: Option<f64>,
+    pub center: Option<Center>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Center {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+/// Parses `<latitude>,<longitude>`.
+impl FromStr for Center {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("expected <latitude>,<longitude>, got '{}'", s);
+
+        let (latitude, longitude) = s.split_once(',').ok_or_else(invalid)?;
+
+        match (latitude.trim().parse(), longitude.trim().parse()) {
+            (Ok(latitude @ -90.0..=90.0), Ok(longitude @ -180.0..=180.0)) => Ok(Center {
+                latitude,
+                longitude,
+            }),
+
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Center {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let text = String::deserialize(deserializer)?;
+
+        text.parse().map_err(de::Error::custom)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    pub south: f64,
+    pub west: f64,
+    pub north: f64,
+    pub east: f64,
+}
+
+impl BoundingBox {
+    /// Smallest box containing all waypoints and points of interest.
+    pub fn of(flightplan: &FlightPlan) -> Option<Self> {
+        let waypoints = flightplan
+            .plan
+            .waypoints
+            .iter()
+            .map(|w| (w.latitude, w.longitude));
+
+        let poi = flightplan
+            .plan
+            .poi
+            .iter()
+            .map(|p| (p.latitude, p.longitude));
+
+        waypoints.chain(poi).fold(None, |bbox, (lat, lon)| {
+            let bbox = bbox.unwrap_or(BoundingBox {
+                south: lat,
+                west: lon,
+                north: lat,
+                east: lon,
+            });
+
+            Some(BoundingBox {
+                south: bbox.south.min(lat),
+                west: bbox.west.min(lon),
+                north: bbox.north.max(lat),
+                east: bbox.east.max(lon),
+            })
+        })
+    }
+
+    pub fn center(&self) -> Center {
+        Center {
+            latitude: (self.south + self.north) / 2.0,
+            longitude: (self.west + self.east) / 2.0,
+        }
+    }
+
+    /// Latitude and longitude extents of the box once centered on `center`.
+    fn spans_around(&self, center: Center) -> (f64, f64) {
+        let latitude = 2.0 * (self.north - center.latitude).max(center.latitude - self.south);
+        let longitude = 2.0 * (self.east - center.longitude).max(center.longitude - self.west);
+
+        (latitude, longitude)
+    }
+
+    /// Closest zoom level showing the whole box around `center`.
+    fn zoom_around(&self, center: Center) -> f64 {
+        let (_, longitude_span) = self.spans_around(center);
+
+        let mercator_span = 2.0
+            * (mercator_y(self.north) - mercator_y(center.latitude))
+                .max(mercator_y(center.latitude) - mercator_y(self.south));
+
+        let zoom_x = (SCREEN_WIDTH_TILES * 360.0 / (longitude_span * MARGIN)).log2();
+        let zoom_y = (SCREEN_HEIGHT_TILES * 2.0 * PI / (mercator_span * MARGIN)).log2();
+
+        zoom_x.min(zoom_y).min(MAX_ZOOM).floor().max(0.0)
+    }
+}
+
+fn mercator_y(latitude: f64) -> f64 {
+    (PI / 4.0 + latitude.to_radians() / 2.0).tan().ln()
+}
+
+impl<'f> FlightPlan<'f> {
+    /// Sets the map viewport so that FreeFlight opens framing the whole plan.
+    pub(super) fn frame(&mut self, viewport: &Viewport) {
+        let bbox = match BoundingBox::of(self) {
+            Some(bbox) => bbox,
+            None => return,
+        };
+
+        let center = viewport.center.unwrap_or_else(|| bbox.center());
+
+        let (latitude_delta, longitude_delta) = bbox.spans_around(center);
+
+        self.latitude = center.latitude;
+        self.longitude = center.longitude;
+
+        self.latitude_delta = latitude_delta * MARGIN;
+        self.longitude_delta = longitude_delta * MARGIN;
+
+        self.zoom_level = viewport.zoom.unwrap_or_else(|| bbox.zoom_around(center));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BoundingBox, Center, MAX_ZOOM};
+
+    #[test]
+    fn zoom_decreases_with_extent() {
+        let point = BoundingBox {
+            south: 48.8566,
+            west: 2.3522,
+            north: 48.8566,
+            east: 2.3522,
+        };
+
+        // roughly 1km x 1km
+        let small = BoundingBox {
+            north: 48.8656,
+            east: 2.3659,
+            ..point
+        };
+
+        // roughly 10km x 10km
+        let large = BoundingBox {
+            north: 48.9466,
+            east: 2.4889,
+            ..point
+        };
+
+        assert_eq!(point.zoom_around(point.center()), MAX_ZOOM);
+        assert_eq!(small.zoom_around(small.center()), 16.0);
+        assert_eq!(large.zoom_around(large.center()), 12.0);
+    }
+
+    #[test]
+    fn off_center_viewport_still_fits() {
+        let bbox = BoundingBox {
+            south: 48.85,
+            west: 2.35,
+            north: 48.86,
+            east: 2.36,
+        };
+
+        let center: Center = "48.85,2.35".parse().unwrap();
+
+        let (latitude_span, longitude_span) = bbox.spans_around(center);
+
+        assert!((latitude_span - 0.02).abs() < 1e-9);
+        assert!((longitude_span - 0.02).abs() < 1e-9);
+        assert!(bbox.zoom_around(center) < bbox.zoom_around(bbox.center()));
+    }
+}
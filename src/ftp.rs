@@ -0,0 +1,338 @@
+//! Just enough of an FTP client to upload files to a drone.
+
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    net::{SocketAddr, TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+use crate::error::Error;
+
+const DEFAULT_PORT: u16 = 21;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug)]
+pub enum FtpError {
+    InputOutput(io::Error),
+    UnknownHost(String),
+
+    /// The server answered `reply` to `command`
+    UnexpectedReply {
+        command: String,
+        reply: String,
+    },
+
+    MalformedReply(String),
+}
+
+impl From<io::Error> for FtpError {
+    fn from(underlying: io::Error) -> Self {
+        FtpError::InputOutput(underlying)
+    }
+}
+
+struct Reply {
+    code: u16,
+    text: String,
+}
+
+pub struct FtpClient {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl FtpClient {
+    /// Connects to `host`, given as `<host>[:<port>]`.
+    pub fn connect(host: &str) -> Result<Self, FtpError> {
+        let address = match host.rsplit_once(':') {
+            Some((_, port)) if port.parse::<u16>().is_ok() => host.to_owned(),
+            _ => format!("{}:{}", host, DEFAULT_PORT),
+        };
+
+        let address = address
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| FtpError::UnknownHost(host.to_owned()))?;
+
+        let stream = TcpStream::connect_timeout(&address, TIMEOUT)?;
+
+        stream.set_read_timeout(Some(TIMEOUT))?;
+
+        let mut client = FtpClient {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+        };
+
+        client.expect("connect", &[220])?;
+
+        Ok(client)
+    }
+
+    pub fn login(&mut self, user: &str, password: &str) -> Result<(), FtpError> {
+        let reply = self.command(&format!("USER {}", user), &[230, 331])?;
+
+        if reply.code == 331 {
+            self.command(&format!("PASS {}", password), &[230, 202])?;
+        }
+
+        Ok(())
+    }
+
+    /// Creates every missing folder along `path`.
+    pub fn make_dirs(&mut self, path: &str) -> Result<(), FtpError> {
+        let mut current = String::new();
+
+        for part in path.split('/').filter(|part| !part.is_empty()) {
+            if !current.is_empty() || path.starts_with('/') {
+                current.push('/');
+            }
+
+            current.push_str(part);
+
+            // Fails when the folder already exists, which is fine.
+            self.command(&format!("MKD {}", current), &[257, 550])?;
+        }
+
+        Ok(())
+    }
+
+    /// Uploads `data` as `path`, replacing any existing file.
+    pub fn store(&mut self, path: &str, data: &[u8]) -> Result<(), FtpError> {
+        self.command("TYPE I", &[200])?;
+
+        let address = self.passive()?;
+
+        let mut data_stream = TcpStream::connect_timeout(&address, TIMEOUT)?;
+
+        self.command(&format!("STOR {}", path), &[125, 150])?;
+
+        data_stream.write_all(data)?;
+
+        drop(data_stream);
+
+        self.expect("STOR", &[226, 250])?;
+
+        Ok(())
+    }
+
+    pub fn quit(mut self) -> Result<(), FtpError> {
+        self.command("QUIT", &[221])?;
+
+        Ok(())
+    }
+
+    /// Asks for a passive data connection. The address the server gives is ignored
+    /// in favor of the one already connected to, as drones often give their internal one.
+    fn passive(&mut self) -> Result<SocketAddr, FtpError> {
+        let reply = self.command("PASV", &[227])?;
+
+        let malformed = || FtpError::MalformedReply(reply.text.clone());
+
+        let numbers = reply.text.split(['(', ')']).nth(1).ok_or_else(malformed)?;
+
+        let numbers: Vec<u16> = numbers
+            .split(',')
+            .map(|n| n.trim().parse())
+            .collect::<Result<_, _>>()
+            .map_err(|_| malformed())?;
+
+        match numbers[..] {
+            [_, _, _, _, high, low] => {
+                let mut address = self.writer.peer_addr()?;
+
+                address.set_port(high * 256 + low);
+
+                Ok(address)
+            }
+
+            _ => Err(malformed()),
+        }
+    }
+
+    fn command(&mut self, command: &str, expected: &[u16]) -> Result<Reply, FtpError> {
+        write!(self.writer, "{}\r\n", command)?;
+
+        self.writer.flush()?;
+
+        self.expect(command, expected)
+    }
+
+    fn expect(&mut self, command: &str, expected: &[u16]) -> Result<Reply, FtpError> {
+        let reply = self.reply()?;
+
+        if expected.contains(&reply.code) {
+            Ok(reply)
+        } else {
+            Err(FtpError::UnexpectedReply {
+                command: command.split(' ').next().unwrap_or_default().to_owned(),
+                reply: format!("{} {}", reply.code, reply.text),
+            })
+        }
+    }
+
+    /// Reads a reply, skipping the continuation lines of multiline ones.
+    fn reply(&mut self) -> Result<Reply, FtpError> {
+        let mut line = self.line()?;
+
+        if line.as_bytes().get(3) == Some(&b'-') {
+            let end = format!("{} ", &line[..3]);
+
+            while !line.starts_with(&end) {
+                line = self.line()?;
+            }
+        }
+
+        let code = line
+            .get(..3)
+            .and_then(|code| code.parse().ok())
+            .ok_or_else(|| FtpError::MalformedReply(line.clone()))?;
+
+        Ok(Reply {
+            code,
+            text: line.get(4..).unwrap_or_default().to_owned(),
+        })
+    }
+
+    fn line(&mut self) -> Result<String, FtpError> {
+        let mut line = String::new();
+
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+
+        Ok(line.trim_end().to_owned())
+    }
+}
+
+/// Uploads `data` to `<dir>/<file_name>` on the FTP server at `host`, logged in anonymously.
+pub fn upload(host: &str, dir: &str, file_name: &str, data: &[u8]) -> Result<String, Error> {
+    let mut client = FtpClient::connect(host)?;
+
+    client.login("anonymous", "")?;
+
+    client.make_dirs(dir)?;
+
+    let path = match dir.trim_end_matches('/') {
+        "" => file_name.to_owned(),
+        dir => format!("{}/{}", dir, file_name),
+    };
+
+    client.store(&path, data)?;
+
+    client.quit()?;
+
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    use super::upload;
+
+    /// Serves a single session, returns the stored files and created folders.
+    fn fake_server(listener: TcpListener) -> (HashMap<String, Vec<u8>>, Vec<String>) {
+        let (control, _) = listener.accept().unwrap();
+
+        let mut reader = BufReader::new(control.try_clone().unwrap());
+        let mut writer = control;
+
+        let mut files = HashMap::new();
+        let mut folders = vec![];
+        let mut data_listener = None;
+
+        writer
+            .write_all(b"220-Fake drone\r\n220 Ready\r\n")
+            .unwrap();
+
+        loop {
+            let mut line = String::new();
+
+            if reader.read_line(&mut line).unwrap() == 0 {
+                break;
+            }
+
+            let (command, argument) = line
+                .trim_end()
+                .split_once(' ')
+                .unwrap_or((line.trim_end(), ""));
+
+            let reply = match command {
+                "USER" => "331 Password required".to_owned(),
+                "PASS" => "230 Logged in".to_owned(),
+                "TYPE" => "200 Binary".to_owned(),
+
+                "MKD" if folders.iter().any(|f| f == argument) => "550 Exists".to_owned(),
+
+                "MKD" => {
+                    folders.push(argument.to_owned());
+
+                    "257 Created".to_owned()
+                }
+
+                "PASV" => {
+                    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+                    let port = listener.local_addr().unwrap().port();
+
+                    data_listener = Some(listener);
+
+                    format!(
+                        "227 Entering Passive Mode (10,0,0,1,{},{})",
+                        port / 256,
+                        port % 256
+                    )
+                }
+
+                "STOR" => {
+                    writer.write_all(b"150 Go ahead\r\n").unwrap();
+
+                    let (mut stream, _) = data_listener.take().unwrap().accept().unwrap();
+
+                    let mut data = vec![];
+
+                    stream.read_to_end(&mut data).unwrap();
+
+                    files.insert(argument.to_owned(), data);
+
+                    "226 Done".to_owned()
+                }
+
+                "QUIT" => {
+                    writer.write_all(b"221 Bye\r\n").unwrap();
+
+                    break;
+                }
+
+                _ => "502 Not implemented".to_owned(),
+            };
+
+            write!(writer, "{}\r\n", reply).unwrap();
+        }
+
+        (files, folders)
+    }
+
+    #[test]
+    fn uploads_to_a_local_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let host = listener.local_addr().unwrap().to_string();
+
+        let server = thread::spawn(move || fake_server(listener));
+
+        let data = b"QGC WPL 120\n".repeat(1000);
+
+        let path = upload(&host, "internal_000/flightplans", "plan.mavlink", &data).unwrap();
+
+        let (files, folders) = server.join().unwrap();
+
+        assert_eq!(path, "internal_000/flightplans/plan.mavlink");
+        assert_eq!(folders, ["internal_000", "internal_000/flightplans"]);
+        assert_eq!(files.get(&path), Some(&data));
+    }
+}
@@ -3,10 +3,22 @@ use serde::{
     Deserialize,
 };
 
+use crate::litchi::csv::de::{Altitude, Coordinates, GimbalSettings, MissionRecord};
+
 use super::{
-    waypoint::Waypoint, FinishAction, PathMode, PhotoInterval, PointOfInterest, WaypointPartial,
+    waypoint::Waypoint, FinishAction, PathMode, PhotoInterval, PointOfInterest, Serializer,
+    WaypointPartial,
 };
 
+/// Cruising speed of missions made of CSV records not setting any speed
+const DEFAULT_SPEED_MS: f32 = 5.0;
+
+/// Maximum speed of Litchi missions, unless a waypoint is flown faster
+const MAX_SPEED_MS: f32 = 15.0;
+
+/// Index of the point of interest of waypoints without any
+const NO_POI: u32 = 0xFFFFFFFF;
+
 #[derive(Debug)]
 pub struct LitchiMission {
     pub finish_action: FinishAction,
@@ -16,6 +28,20 @@ pub struct LitchiMission {
 
     pub waypoints: Vec<Waypoint>,
     pub poi: Vec<PointOfInterest>,
+
+    /// Photo interval of the mission as a whole, next to the waypoints' own
+    pub interval: Option<PhotoInterval>,
+
+    pub unknown: Unknown,
+}
+
+/// Fields of the mission whose meaning is unknown, written back as read,
+/// and zeroed in missions made of CSV records.
+#[derive(Debug, Default)]
+pub struct Unknown {
+    header: u32,
+    settings: [u32; 4],
+    other_settings: [u32; 3],
 }
 
 #[derive(Deserialize, Debug)]
@@ -94,17 +120,21 @@ impl<'de> Visitor<'de> for MissionVisitor {
         for i in 0..nb_waypoints {
             let wp_detail: WaypointDetails = seq
                 .next_element()?
-                .ok_or_else(|| de::Error::invalid_length(i as usize, &"more waypoint details"))?;
+                .ok_or_else(|| de::Error::invalid_length(i, &"more waypoint details"))?;
             wp_details.push(wp_detail);
         }
 
-        for i in 0..mission_part.poi.len() {
-            let _poi_detail: PoIDetails = seq
+        let mut poi = mission_part.poi;
+
+        for (i, poi) in poi.iter_mut().enumerate() {
+            let poi_detail: PoIDetails = seq
                 .next_element()?
-                .ok_or_else(|| de::Error::invalid_length(i as usize, &"more poi details"))?;
+                .ok_or_else(|| de::Error::invalid_length(i, &"more poi details"))?;
+
+            poi.above_ground = poi_detail.is_above_ground != 0;
         }
 
-        let _other_settings: OtherSettings = seq
+        let other_settings: OtherSettings = seq
             .next_element()?
             .ok_or_else(|| de::Error::missing_field("other settings"))?;
 
@@ -113,7 +143,7 @@ impl<'de> Visitor<'de> for MissionVisitor {
         for i in 0..nb_waypoints {
             let interval: (f32, f32) = seq
                 .next_element()?
-                .ok_or_else(|| de::Error::invalid_length(i as usize, &"more intervals"))?;
+                .ok_or_else(|| de::Error::invalid_length(i, &"more intervals"))?;
 
             wp_intervals.push(interval)
         }
@@ -122,11 +152,11 @@ impl<'de> Visitor<'de> for MissionVisitor {
             .waypoints
             .into_iter()
             .zip(wp_details.iter())
-            .zip(wp_intervals.into_iter())
+            .zip(wp_intervals)
             .map(|((waypoint, details), intervals)| {
                 let interval = PhotoInterval::from_tuple(intervals);
 
-                let poi = (details.waypoint_poi != 0xFFFFFFFF).then_some(details.waypoint_poi);
+                let poi = (details.waypoint_poi != NO_POI).then_some(details.waypoint_poi);
 
                 Waypoint {
                     altitude: waypoint.altitude,
@@ -136,8 +166,10 @@ impl<'de> Visitor<'de> for MissionVisitor {
                     curve_size: waypoint.curve_size,
                     gimbal: waypoint.gimbal,
                     poi,
+                    above_ground: details.is_above_ground != 0,
                     interval,
                     actions: waypoint.actions,
+                    unknown: waypoint.unknown,
                 }
             })
             .collect();
@@ -148,7 +180,20 @@ impl<'de> Visitor<'de> for MissionVisitor {
             cruising_speed: mission_part.cruising_speed,
             max_speed: mission_part.max_speed,
             waypoints,
-            poi: mission_part.poi,
+            poi,
+            interval: PhotoInterval::from_tuple((
+                other_settings.photo_capture_interval_seconds,
+                other_settings.photo_capture_interval_meters,
+            )),
+            unknown: Unknown {
+                header: mission_part._plop,
+                settings: mission_part._b,
+                other_settings: [
+                    other_settings.__u32_1,
+                    other_settings.__u32_2,
+                    other_settings.__u32_3,
+                ],
+            },
         };
 
         Ok(mission)
@@ -178,4 +223,399 @@ impl PhotoInterval {
             None
         }
     }
+
+    fn to_tuple(interval: Option<Self>) -> (f32, f32) {
+        match interval {
+            Some(PhotoInterval::Time { seconds }) => (seconds, -1.0),
+            Some(PhotoInterval::Distance { meters }) => (-1.0, meters),
+            None => (-1.0, -1.0),
+        }
+    }
+}
+
+impl LitchiMission {
+    /// Writes the mission after the magic, in the order it is read.
+    pub(super) fn serialize(&self, serializer: &mut Serializer) {
+        serializer.put_u32(self.unknown.header);
+        serializer.put_u32(self.finish_action as u32);
+        serializer.put_u32(self.path_mode as u32);
+        serializer.put_float(self.cruising_speed);
+        serializer.put_float(self.max_speed);
+
+        for value in self.unknown.settings {
+            serializer.put_u32(value);
+        }
+
+        serializer.put_u32(self.waypoints.len() as u32);
+
+        for waypoint in &self.waypoints {
+            let (gimbal_mode, gimbal_angle) = GimbalSettings::to_tuple(waypoint.gimbal.as_ref());
+            let [u32_1, u32_2, u32_3, trash] = waypoint.unknown;
+
+            serializer.put_float(waypoint.altitude);
+            serializer.put_u32(u32_1);
+            serializer.put_float(waypoint.heading);
+            serializer.put_u32(u32_2);
+            serializer.put_u32(u32_3);
+            serializer.put_double(waypoint.latitude);
+            serializer.put_double(waypoint.longitude);
+            serializer.put_float(waypoint.curve_size);
+            serializer.put_u32(gimbal_mode);
+            serializer.put_u32(gimbal_angle as u32);
+            serializer.put_u32(waypoint.actions.len() as u32);
+            serializer.put_u32(trash);
+
+            for action in &waypoint.actions {
+                let (code, value) = <(u32, u32)>::from(action);
+
+                serializer.put_u32(code);
+                serializer.put_u32(value);
+            }
+        }
+
+        serializer.put_u32(self.poi.len() as u32);
+
+        for poi in &self.poi {
+            serializer.put_double(poi.latitude);
+            serializer.put_double(poi.longitude);
+            serializer.put_float(poi.altitude);
+        }
+
+        for waypoint in &self.waypoints {
+            serializer.put_u16(waypoint.above_ground as u16);
+            serializer.put_float(waypoint.altitude);
+            serializer.put_u32(waypoint.poi.unwrap_or(NO_POI));
+        }
+
+        for poi in &self.poi {
+            serializer.put_u16(poi.above_ground as u16);
+            serializer.put_float(poi.altitude);
+        }
+
+        for value in self.unknown.other_settings {
+            serializer.put_u32(value);
+        }
+
+        let intervals = std::iter::once(self.interval)
+            .chain(self.waypoints.iter().map(|waypoint| waypoint.interval));
+
+        for interval in intervals {
+            let (seconds, meters) = PhotoInterval::to_tuple(interval);
+
+            serializer.put_float(seconds);
+            serializer.put_float(meters);
+        }
+    }
+
+    /// What CSV records made of this mission lose.
+    pub fn csv_losses(&self) -> Vec<String> {
+        let mut losses = vec![];
+
+        if self.finish_action != FinishAction::None {
+            losses.push(format!(
+                "finish action {:?} dropped, CSV missions have none",
+                self.finish_action
+            ));
+        }
+
+        if self.interval.is_some() {
+            losses.push("photo interval of the mission dropped, only waypoints keep theirs".into());
+        }
+
+        for (index, waypoint) in self.waypoints.iter().enumerate() {
+            let value = match waypoint.interval {
+                Some(PhotoInterval::Time { seconds }) => seconds,
+                Some(PhotoInterval::Distance { meters }) => meters,
+                None => continue,
+            };
+
+            if value.round() > i8::MAX as f32 {
+                losses.push(format!(
+                    "photo interval of waypoint {} cut from {} to {}",
+                    index + 1,
+                    value,
+                    i8::MAX
+                ));
+            }
+        }
+
+        losses
+    }
+
+    /// What a mission made of `records` loses.
+    pub fn bin_losses(records: &[MissionRecord]) -> Vec<String> {
+        let mission = LitchiMission::from(records);
+
+        let speeds: Vec<_> = records
+            .iter()
+            .map(|record| record.speed)
+            .filter(|speed| *speed > 0.0 && *speed != mission.cruising_speed)
+            .collect();
+
+        if speeds.is_empty() {
+            return vec![];
+        }
+
+        vec![format!(
+            "{} waypoint speeds replaced by the cruising speed of {} m/s",
+            speeds.len(),
+            mission.cruising_speed
+        )]
+    }
+}
+
+/// Every record flies at the cruising speed of the mission.
+impl From<&LitchiMission> for Vec<MissionRecord> {
+    fn from(mission: &LitchiMission) -> Self {
+        mission
+            .waypoints
+            .iter()
+            .map(|waypoint| {
+                let height = waypoint.altitude.round() as u16;
+
+                let (photo_timeinterval, photo_distinterval) = match waypoint.interval {
+                    Some(PhotoInterval::Time { seconds }) => (seconds.round() as i8, -1),
+                    Some(PhotoInterval::Distance { meters }) => (-1, meters.round() as i8),
+                    None => (-1, -1),
+                };
+
+                MissionRecord {
+                    waypoint: Coordinates {
+                        latitude: waypoint.latitude,
+                        longitude: waypoint.longitude,
+                        altitude: if waypoint.above_ground {
+                            Altitude::AboveGround(height)
+                        } else {
+                            Altitude::Absolute(height)
+                        },
+                    },
+                    heading: widen(waypoint.heading).rem_euclid(360.0),
+                    curvesize: match mission.path_mode {
+                        PathMode::StraigtLines => 0.0,
+                        PathMode::CurvedTurns => widen(waypoint.curve_size),
+                    },
+                    rotationdir: 0,
+                    gimbal: waypoint.gimbal.clone(),
+                    speed: mission.cruising_speed,
+                    poi: waypoint
+                        .poi
+                        .and_then(|index| mission.poi.get(index as usize))
+                        .map(|poi| {
+                            let height = poi.altitude.round() as u16;
+
+                            Coordinates {
+                                latitude: poi.latitude,
+                                longitude: poi.longitude,
+                                altitude: if poi.above_ground {
+                                    Altitude::AboveGround(height)
+                                } else {
+                                    Altitude::Absolute(height)
+                                },
+                            }
+                        }),
+                    photo_timeinterval,
+                    photo_distinterval,
+                    actions: waypoint.actions.clone(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Records share their points of interest, and the first speed set is the cruising speed:
+/// bin missions have no speed per waypoint. Missions end without any finish action.
+impl From<&[MissionRecord]> for LitchiMission {
+    fn from(records: &[MissionRecord]) -> Self {
+        let mut poi: Vec<PointOfInterest> = vec![];
+
+        let waypoints = records
+            .iter()
+            .map(|record| {
+                let index = record.poi.as_ref().map(|coordinates| {
+                    let position = poi.iter().position(|known| {
+                        known.latitude == coordinates.latitude
+                            && known.longitude == coordinates.longitude
+                    });
+
+                    position.unwrap_or_else(|| {
+                        poi.push(PointOfInterest {
+                            latitude: coordinates.latitude,
+                            longitude: coordinates.longitude,
+                            altitude: height(&coordinates.altitude),
+                            above_ground: matches!(coordinates.altitude, Altitude::AboveGround(_)),
+                        });
+
+                        poi.len() - 1
+                    }) as u32
+                });
+
+                let interval = if record.photo_timeinterval > 0 {
+                    Some(PhotoInterval::Time {
+                        seconds: record.photo_timeinterval as f32,
+                    })
+                } else if record.photo_distinterval > 0 {
+                    Some(PhotoInterval::Distance {
+                        meters: record.photo_distinterval as f32,
+                    })
+                } else {
+                    None
+                };
+
+                Waypoint {
+                    altitude: height(&record.waypoint.altitude),
+                    heading: record.heading as f32,
+                    latitude: record.waypoint.latitude,
+                    longitude: record.waypoint.longitude,
+                    curve_size: record.curvesize as f32,
+                    gimbal: record.gimbal.clone(),
+                    poi: index,
+                    above_ground: matches!(record.waypoint.altitude, Altitude::AboveGround(_)),
+                    interval,
+                    actions: record.actions.clone(),
+                    unknown: [0; 4],
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let speeds = records.iter().map(|record| record.speed);
+
+        let cruising_speed = speeds
+            .clone()
+            .find(|speed| *speed > 0.0)
+            .unwrap_or(DEFAULT_SPEED_MS);
+
+        let curved = waypoints.iter().any(|waypoint| waypoint.curve_size > 0.0);
+
+        LitchiMission {
+            finish_action: FinishAction::None,
+            path_mode: if curved {
+                PathMode::CurvedTurns
+            } else {
+                PathMode::StraigtLines
+            },
+            cruising_speed,
+            max_speed: speeds.fold(MAX_SPEED_MS, f32::max),
+            waypoints,
+            poi,
+            interval: None,
+            unknown: Unknown::default(),
+        }
+    }
+}
+
+/// The value with the centimeter precision of Litchi, without the noise of `f32`,
+/// e.g. 0.2 rather than 0.20000000298023224.
+fn widen(value: f32) -> f64 {
+    (value as f64 * 100.0).round() / 100.0
+}
+
+fn height(altitude: &Altitude) -> f32 {
+    match altitude {
+        Altitude::AboveGround(height) | Altitude::Absolute(height) => *height as f32,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::litchi::{
+        bin,
+        csv::{de::MissionRecord, parse, MISSION},
+    };
+
+    use super::{LitchiMission, Serializer};
+
+    #[test]
+    fn records_survive_a_round_trip_through_bin() {
+        let mut records = parse(MISSION.as_bytes());
+
+        let mission = LitchiMission::from(records.as_slice());
+
+        assert_eq!(mission.poi.len(), 2);
+        assert_eq!(LitchiMission::bin_losses(&records).len(), 1);
+
+        let read_back = bin::from_slice(&bin::to_vec(&mission)).unwrap();
+
+        let records_back = Vec::<MissionRecord>::from(&read_back);
+
+        assert!(read_back.csv_losses().is_empty());
+
+        // All but the speeds, flown at the cruising speed of the first record
+        assert_eq!(records_back[1].speed, 7.0);
+        records[1].speed = 7.0;
+
+        assert_eq!(format!("{:?}", records), format!("{:?}", records_back));
+    }
+
+    /// A mission laid out as Mission Hub writes them, with values in the fields not understood.
+    fn mission_hub_bin() -> Vec<u8> {
+        let mut bin = Serializer::new();
+
+        bin.put_u32(super::super::MAGIC);
+
+        // Header, RTH, curved turns, 6.5 m/s cruising, 12 m/s max, unknown settings
+        for value in [7, 1, 1] {
+            bin.put_u32(value);
+        }
+
+        bin.put_float(6.5);
+        bin.put_float(12.0);
+
+        for value in [1, 2, 3, 4] {
+            bin.put_u32(value);
+        }
+
+        // A waypoint looking at the point of interest and taking a photo
+        bin.put_u32(1);
+        bin.put_float(35.0);
+        bin.put_u32(0x11);
+        bin.put_float(45.0);
+        bin.put_u32(0x22);
+        bin.put_u32(0x33);
+        bin.put_double(48.8566);
+        bin.put_double(2.3522);
+        bin.put_float(2.0);
+        bin.put_u32(1);
+        bin.put_u32(-20i32 as u32);
+        bin.put_u32(1);
+        bin.put_u32(0x44);
+        bin.put_u32(1);
+        bin.put_u32(0);
+
+        // The point of interest
+        bin.put_u32(1);
+        bin.put_double(48.857);
+        bin.put_double(2.353);
+        bin.put_float(12.0);
+
+        // Details: the waypoint's, then the point of interest's, both above ground
+        bin.put_u16(1);
+        bin.put_float(35.0);
+        bin.put_u32(0);
+        bin.put_u16(1);
+        bin.put_float(12.0);
+
+        // Other settings, a photo every 20 m, then a photo every 200 s at the waypoint
+        for value in [5, 6, 7] {
+            bin.put_u32(value);
+        }
+
+        for value in [-1.0, 20.0, 200.0, -1.0] {
+            bin.put_float(value);
+        }
+
+        bin.into_inner()
+    }
+
+    #[test]
+    fn bin_missions_are_written_back_as_read() {
+        let data = mission_hub_bin();
+
+        let mission = bin::from_slice(&data).unwrap();
+
+        assert!(mission.poi[0].above_ground);
+        assert_eq!(bin::to_vec(&mission), data);
+
+        // RTH, the photo interval of the mission, and 200 s cut to 127 s
+        assert_eq!(mission.csv_losses().len(), 3);
+    }
 }
@@ -2,6 +2,7 @@ use serde::Deserialize;
 use serde_repr::{Deserialize_repr, Serialize_repr};
 
 use deserializer::Deserializer;
+use serializer::Serializer;
 
 pub use self::mission::LitchiMission;
 pub use self::waypoint::WaypointPartial;
@@ -11,11 +12,12 @@ pub use error::Error;
 mod deserializer;
 mod error;
 mod mission;
+mod serializer;
 mod waypoint;
 
 const MAGIC: u32 = 0x6C_63_68_6D; //b"lchm"
 
-#[derive(Serialize_repr, Deserialize_repr, PartialEq, Eq, Debug)]
+#[derive(Serialize_repr, Deserialize_repr, PartialEq, Eq, Debug, Clone, Copy)]
 #[repr(u32)]
 pub enum FinishAction {
     None = 0,
@@ -25,7 +27,7 @@ pub enum FinishAction {
     Reverse = 4,
 }
 
-#[derive(Serialize_repr, Deserialize_repr, PartialEq, Eq, Debug)]
+#[derive(Serialize_repr, Deserialize_repr, PartialEq, Eq, Debug, Clone, Copy)]
 #[repr(u32)]
 pub enum PathMode {
     StraigtLines = 0,
@@ -38,9 +40,13 @@ pub struct PointOfInterest {
     pub longitude: f64,
 
     pub altitude: f32,
+
+    /// Altitude above ground rather than above the takeoff point, read from the details
+    #[serde(skip)]
+    pub above_ground: bool,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum PhotoInterval {
     Time { seconds: f32 },
     Distance { meters: f32 },
@@ -51,3 +57,13 @@ pub fn from_slice(bytes: &[u8]) -> Result<LitchiMission, Error> {
 
     LitchiMission::deserialize(&mut deserializer)
 }
+
+pub fn to_vec(mission: &LitchiMission) -> Vec<u8> {
+    let mut serializer = Serializer::new();
+
+    serializer.put_u32(MAGIC);
+
+    mission.serialize(&mut serializer);
+
+    serializer.into_inner()
+}
@@ -0,0 +1,30 @@
+/// Writes values the way `Deserializer` reads them: big endian, sequences prefixed by their length.
+pub struct Serializer {
+    output: Vec<u8>,
+}
+
+impl Serializer {
+    pub fn new() -> Self {
+        Serializer { output: vec![] }
+    }
+
+    pub fn put_u16(&mut self, value: u16) {
+        self.output.extend(value.to_be_bytes());
+    }
+
+    pub fn put_u32(&mut self, value: u32) {
+        self.output.extend(value.to_be_bytes());
+    }
+
+    pub fn put_float(&mut self, value: f32) {
+        self.output.extend(value.to_be_bytes());
+    }
+
+    pub fn put_double(&mut self, value: f64) {
+        self.output.extend(value.to_be_bytes());
+    }
+
+    pub fn into_inner(self) -> Vec<u8> {
+        self.output
+    }
+}
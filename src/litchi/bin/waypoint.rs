@@ -33,6 +33,8 @@ pub struct WaypointPartial {
     pub gimbal: Option<GimbalSettings>,
 
     pub actions: Vec<Action>,
+
+    pub unknown: [u32; 4],
 }
 
 impl WaypointPartial {
@@ -45,6 +47,7 @@ impl WaypointPartial {
             curve_size: raw.curve_size,
             gimbal: GimbalSettings::from_tuple(raw.gimbal_settings),
             actions,
+            unknown: [raw._u32_1, raw._u32_2, raw._u32_3, raw.__trash],
         }
     }
 }
@@ -60,9 +63,15 @@ pub struct Waypoint {
 
     pub poi: Option<u32>,
 
+    /// Altitude above ground rather than above the takeoff point
+    pub above_ground: bool,
+
     pub interval: Option<PhotoInterval>,
 
     pub actions: Vec<Action>,
+
+    /// Fields whose meaning is unknown, in the order of the file, written back as read
+    pub unknown: [u32; 4],
 }
 
 impl GimbalSettings {
@@ -77,6 +86,14 @@ impl GimbalSettings {
             _ => panic!("unknown gimbal settings"),
         }
     }
+
+    pub(super) fn to_tuple(settings: Option<&Self>) -> (u32, i32) {
+        match settings {
+            None => (0x0, 0),
+            Some(GimbalSettings::FocusPoi(angle)) => (0x1, *angle as i32),
+            Some(GimbalSettings::Interpolate(angle)) => (0x2, *angle as i32),
+        }
+    }
 }
 
 struct WaypointVisitor;
@@ -140,3 +157,16 @@ impl From<(u32, u32)> for Action {
         }
     }
 }
+
+impl From<&Action> for (u32, u32) {
+    fn from(action: &Action) -> Self {
+        match action {
+            Action::StayFor { ms } => (0, *ms as u32),
+            Action::TakePhoto => (1, 0),
+            Action::StartRecording => (2, 0),
+            Action::StopRecording => (3, 0),
+            Action::RotateAircraft { angle } => (4, *angle as u32),
+            Action::TiltCamera { angle } => (5, *angle as i32 as u32),
+        }
+    }
+}
@@ -24,20 +24,20 @@ pub struct MissionRecord {
     pub actions: Vec<Action>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum GimbalSettings {
     FocusPoi(f64),
     Interpolate(f64),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Coordinates {
     pub latitude: f64,
     pub longitude: f64,
     pub altitude: Altitude,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Altitude {
     AboveGround(u16),
     Absolute(u16),
@@ -1,3 +1,22 @@
 pub mod de;
+pub mod ser;
 
 pub use de::*;
+
+/// A mission of tests: two waypoints recording a video at different speeds, each one
+/// looking at its own point of interest, the first one with a curve, the second one
+/// tilting the camera gradually.
+#[cfg(test)]
+pub(crate) const MISSION: &str = "\
+latitude,longitude,altitude(m),heading(deg),curvesize(m),rotationdir,gimbalmode,gimbalpitchangle,actiontype1,actionparam1,actiontype2,actionparam2,altitudemode,speed(m/s),poi_latitude,poi_longitude,poi_altitude(m),poi_altitudemode,photo_timeinterval,photo_distinterval
+48.8566,2.3522,30,270,1.5,0,1,-30,2,0,0,2000,0,7,48.857,2.353,10,0,-1,-1
+48.8576,2.3532,40,180,0,0,2,-60,3,0,5,-45,0,9,48.858,2.354,15,1,3,-1
+";
+
+#[cfg(test)]
+pub(crate) fn parse(csv: &[u8]) -> Vec<MissionRecord> {
+    csv::Reader::from_reader(csv)
+        .deserialize()
+        .collect::<Result<_, _>>()
+        .unwrap()
+}
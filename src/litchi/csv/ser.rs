@@ -0,0 +1,158 @@
+use std::io::Write;
+
+use crate::litchi::Action;
+
+use super::de::{Altitude, Coordinates, GimbalSettings, MissionRecord};
+
+/// Litchi always exports this many action columns.
+const ACTION_COLUMNS: usize = 15;
+
+/// Writes records in the same layout as Litchi Mission Hub's CSV export.
+pub fn to_writer<W: Write>(records: &[MissionRecord], writer: W) -> Result<(), csv::Error> {
+    let action_columns = records
+        .iter()
+        .map(|r| r.actions.len())
+        .chain(Some(ACTION_COLUMNS))
+        .max()
+        .unwrap_or(ACTION_COLUMNS);
+
+    let mut writer = csv::Writer::from_writer(writer);
+
+    writer.write_record(header(action_columns))?;
+
+    for record in records {
+        writer.write_record(fields(record, action_columns))?;
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+fn header(action_columns: usize) -> Vec<String> {
+    let head = [
+        "latitude",
+        "longitude",
+        "altitude(m)",
+        "heading(deg)",
+        "curvesize(m)",
+        "rotationdir",
+        "gimbalmode",
+        "gimbalpitchangle",
+    ];
+
+    let actions = (1..=action_columns)
+        .flat_map(|i| [format!("actiontype{}", i), format!("actionparam{}", i)]);
+
+    let tail = [
+        "altitudemode",
+        "speed(m/s)",
+        "poi_latitude",
+        "poi_longitude",
+        "poi_altitude(m)",
+        "poi_altitudemode",
+        "photo_timeinterval",
+        "photo_distinterval",
+    ];
+
+    head.into_iter()
+        .map(str::to_owned)
+        .chain(actions)
+        .chain(tail.into_iter().map(str::to_owned))
+        .collect()
+}
+
+fn fields(record: &MissionRecord, action_columns: usize) -> Vec<String> {
+    let (gimbal_mode, gimbal_pitch) = match record.gimbal {
+        None => (0, 0.0),
+        Some(GimbalSettings::FocusPoi(pitch)) => (1, pitch),
+        Some(GimbalSettings::Interpolate(pitch)) => (2, pitch),
+    };
+
+    let (height, altitude_mode) = altitude(&record.waypoint.altitude);
+
+    let mut fields = vec![
+        record.waypoint.latitude.to_string(),
+        record.waypoint.longitude.to_string(),
+        height.to_string(),
+        record.heading.to_string(),
+        record.curvesize.to_string(),
+        record.rotationdir.to_string(),
+        gimbal_mode.to_string(),
+        gimbal_pitch.to_string(),
+    ];
+
+    for i in 0..action_columns {
+        let (action_type, action_param) = record.actions.get(i).map(action).unwrap_or((-1, 0));
+
+        fields.push(action_type.to_string());
+        fields.push(action_param.to_string());
+    }
+
+    fields.push(altitude_mode.to_string());
+    fields.push(record.speed.to_string());
+
+    match &record.poi {
+        Some(Coordinates {
+            latitude,
+            longitude,
+            altitude: poi_altitude,
+        }) => {
+            let (poi_altitude, poi_altitude_mode) = altitude(poi_altitude);
+
+            fields.push(latitude.to_string());
+            fields.push(longitude.to_string());
+            fields.push(poi_altitude.to_string());
+            fields.push(poi_altitude_mode.to_string());
+        }
+
+        None => fields.extend(["0", "0", "0", "0"].map(str::to_owned)),
+    }
+
+    fields.push(record.photo_timeinterval.to_string());
+    fields.push(record.photo_distinterval.to_string());
+
+    fields
+}
+
+fn altitude(altitude: &Altitude) -> (u16, u8) {
+    match altitude {
+        Altitude::Absolute(height) => (*height, 0),
+        Altitude::AboveGround(height) => (*height, 1),
+    }
+}
+
+fn action(action: &Action) -> (i8, isize) {
+    match action {
+        Action::StayFor { ms } => (0, *ms as isize),
+        Action::TakePhoto => (1, 0),
+        Action::StartRecording => (2, 0),
+        Action::StopRecording => (3, 0),
+        Action::RotateAircraft { angle } => (4, *angle as isize),
+        Action::TiltCamera { angle } => (5, *angle as isize),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::litchi::{
+        csv::{parse, MISSION},
+        Action,
+    };
+
+    #[test]
+    fn written_records_read_back() {
+        let records = parse(MISSION.as_bytes());
+
+        let mut written = vec![];
+        super::to_writer(&records, &mut written).unwrap();
+
+        let read_back = parse(&written);
+
+        assert_eq!(format!("{:?}", records), format!("{:?}", read_back));
+        assert!(matches!(
+            read_back[0].actions.as_slice(),
+            [Action::StartRecording, Action::StayFor { ms: 2000 }]
+        ));
+    }
+}
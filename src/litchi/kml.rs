@@ -1,10 +1,14 @@
+use std::{collections::HashMap, io::Write};
+
 use kml::{
-    types::{Element, Geometry, LineString, Placemark, Point},
-    Kml,
+    types::{AltitudeMode, Coord, Element, Geometry, KmlDocument, LineString, Placemark, Point},
+    Kml, KmlWriter,
 };
 
 use crate::error::Error;
 
+use super::csv::de::{Altitude, MissionRecord};
+
 type Result<T> = std::result::Result<T, Error>;
 
 pub struct Mission<'kml> {
@@ -33,7 +37,7 @@ impl<'kml> TryFrom<&'kml Kml> for Mission<'kml> {
     }
 }
 
-fn parse_document(doc: &[Kml]) -> Result<Mission> {
+fn parse_document(doc: &[Kml]) -> Result<Mission<'_>> {
     let mut name: Option<&String> = None;
     let mut start: Option<&Point> = None;
     let mut end: Option<&Point> = None;
@@ -60,10 +64,8 @@ fn parse_document(doc: &[Kml]) -> Result<Mission> {
                 None => todo!(),
             },
 
-            Kml::Element(elt) => {
-                if name.is_none() {
-                    name = try_parse_name(elt);
-                }
+            Kml::Element(elt) if name.is_none() => {
+                name = try_parse_name(elt);
             }
 
             _ => (),
@@ -88,7 +90,7 @@ enum PlaceMarkType<'e> {
     Path(&'e LineString),
 }
 
-fn try_parse_placemark(elt: &Placemark) -> Option<PlaceMarkType> {
+fn try_parse_placemark(elt: &Placemark) -> Option<PlaceMarkType<'_>> {
     use PlaceMarkType::*;
 
     match &elt.geometry {
@@ -112,3 +114,82 @@ fn try_parse_name(elt: &Element) -> Option<&String> {
         None
     }
 }
+
+/// Builds a KML document laid out like Litchi Mission Hub's export.
+pub fn to_kml(name: &str, records: &[MissionRecord]) -> Kml {
+    let coords: Vec<_> = records
+        .iter()
+        .map(|r| {
+            let height = match r.waypoint.altitude {
+                Altitude::AboveGround(x) | Altitude::Absolute(x) => x,
+            };
+
+            Coord::new(
+                r.waypoint.longitude,
+                r.waypoint.latitude,
+                Some(height as f64),
+            )
+        })
+        .collect();
+
+    let altitude_mode = match records.first().map(|r| &r.waypoint.altitude) {
+        Some(Altitude::Absolute(_)) => AltitudeMode::Absolute,
+        _ => AltitudeMode::RelativeToGround,
+    };
+
+    let point = |name: &str, coord: Option<&Coord>| {
+        Kml::Placemark(Placemark {
+            name: Some(name.to_owned()),
+            geometry: coord.map(|coord| {
+                Geometry::Point(Point {
+                    coord: *coord,
+                    altitude_mode,
+                    ..Default::default()
+                })
+            }),
+            ..Default::default()
+        })
+    };
+
+    let start = point("Start Point", coords.first());
+    let end = point("End Point", coords.last());
+
+    let path = Kml::Placemark(Placemark {
+        name: Some(name.to_owned()),
+        geometry: Some(Geometry::LineString(LineString {
+            coords,
+            altitude_mode,
+            tessellate: true,
+            ..Default::default()
+        })),
+        ..Default::default()
+    });
+
+    let name = Kml::Element(Element {
+        name: "name".to_owned(),
+        content: Some(name.to_owned()),
+        ..Default::default()
+    });
+
+    let document = Kml::Document {
+        attrs: HashMap::new(),
+        elements: vec![name, start, end, path],
+    };
+
+    Kml::KmlDocument(KmlDocument {
+        attrs: HashMap::from([(
+            "xmlns".to_owned(),
+            "http://www.opengis.net/kml/2.2".to_owned(),
+        )]),
+        elements: vec![document],
+        ..Default::default()
+    })
+}
+
+pub fn to_writer<W: Write>(kml: &Kml, mut writer: W) -> Result<()> {
+    writer.write_all(b"<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n")?;
+
+    KmlWriter::<_, f64>::from_writer(writer).write(kml)?;
+
+    Ok(())
+}
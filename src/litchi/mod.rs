@@ -2,7 +2,7 @@ pub mod bin;
 pub mod csv;
 pub mod kml;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Action {
     StayFor { ms: usize },
     TakePhoto,
@@ -1,27 +1,107 @@
-use std::io::Read;
+use std::path::{Path, PathBuf};
 use std::{ffi::OsStr, fs};
-use std::{path::Path, vec};
 
 use clap::error::ErrorKind;
 use clap::{CommandFactory, Parser, Subcommand};
-use flightplan::FlightPlan;
+use flightplan::{
+    Center, FeatureCollection, FlightPlan, Gpx, PhotoMode, Settings, Takeoff, UuidMode,
+    VideoResolution,
+};
 use kml::KmlReader;
 
+use crate::device::DeviceCommand;
 use crate::litchi::csv::de::MissionRecord;
+use crate::mavlink::MissionItem;
+use crate::output::Destination;
+use crate::store::{ConflictPolicy, Location};
+use crate::wpml::Aircraft;
 use crate::{error::Error, litchi::kml::Mission};
 
+mod convert;
+mod device;
 mod error;
 mod flightplan;
+mod ftp;
 mod litchi;
+mod mavlink;
 mod mtp;
+mod output;
+mod store;
+mod sync;
+mod wpml;
 
 /// Converts Litchi Mission exports (KML, CSV) to Parrot FreeFlight's JSON format for the FlightPlan feature.
 #[derive(Parser, Debug)]
 #[clap(about, version, author = "Nic0w")]
 struct CommandLineInterface {
+    /// Store the plans on the device, or in the `--mount` directory
     #[clap(short, long)]
     store: bool,
 
+    /// Write the plans to a directory, as `flightPlan/<uuid>/savedPlan.json`,
+    /// to a `.zip` bundle laid out the same, to a `.kml` document for review,
+    /// to a `.geojson`, `.gpx`, QGroundControl `.plan` or Mission Planner `.waypoints` file,
+    /// or to a single `.json` file
+    #[clap(short, long)]
+    output: Option<PathBuf>,
+
+    /// Elevation in meters above sea level of the takeoff point,
+    /// to write absolute altitudes in exported files, and to read those of GPX files
+    #[clap(long, allow_hyphen_values = true)]
+    takeoff_elevation: Option<f64>,
+
+    #[clap(flatten)]
+    location: Location,
+
+    /// Upload the plan as a MAVLink mission straight to the drone's FTP server, as `<host>[:<port>]`
+    #[clap(long, conflicts_with = "store")]
+    to_drone: Option<String>,
+
+    /// Folder of the drone's FTP server receiving MAVLink missions
+    #[clap(long, default_value = "internal_000/flightplans")]
+    drone_dir: String,
+
+    /// What to do when the plan is already stored on the device
+    #[clap(long, value_enum, default_value_t)]
+    on_conflict: ConflictPolicy,
+
+    /// JSON file with conversion settings, overridden by command line options
+    #[clap(short, long)]
+    profile: Option<String>,
+
+    /// Resolution of recorded videos
+    #[clap(long, value_enum)]
+    video_resolution: Option<VideoResolution>,
+
+    /// Frame rate of recorded videos
+    #[clap(long)]
+    fps: Option<u8>,
+
+    /// Format of taken pictures
+    #[clap(long, value_enum)]
+    photo_mode: Option<PhotoMode>,
+
+    /// Actions performed after takeoff, comma separated:
+    /// `none`, `video`, `timelapse[:<seconds>]`, `tilt:<degrees>`
+    #[clap(long)]
+    takeoff: Option<Takeoff>,
+
+    /// Zoom level of the map when opening the plan, computed to fit the whole plan by default
+    #[clap(long)]
+    zoom: Option<f64>,
+
+    /// Center of the map when opening the plan, as `<latitude>,<longitude>`
+    #[clap(long, allow_hyphen_values = true)]
+    center: Option<Center>,
+
+    /// Generate a random UUID instead of deriving it from the mission's content
+    #[clap(long)]
+    random_uuid: bool,
+
+    /// Distance in meters under which two points of interest are merged
+    #[clap(long)]
+    poi_tolerance: Option<f64>,
+
     #[clap(subcommand)]
     command: Commands,
 }
@@ -30,14 +110,14 @@ struct CommandLineInterface {
 enum Commands {
     /// To convert a KML file
     Kml {
-        /// Input file
-        file: Option<String>,
+        /// Input files
+        files: Vec<String>,
     },
 
     /// To convert a CSV file
     Csv {
-        /// Input file
-        file: Option<String>,
+        /// Input files
+        files: Vec<String>,
 
         /// Mission name
         #[clap(short, long)]
@@ -46,57 +126,370 @@ enum Commands {
 
     /// To convert a bin file
     Bin {
-        /// Input file
-        file: Option<String>,
+        /// Input files
+        files: Vec<String>,
+
+        /// Mission name
+        #[clap(short, long)]
+        title: Option<String>,
+    },
+
+    /// To convert a GeoJSON file, e.g. a plan exported then edited in a GIS
+    Geojson {
+        /// Input files
+        files: Vec<String>,
+
+        /// Mission name, when the collection's `name` is missing
+        #[clap(short, long)]
+        title: Option<String>,
+    },
+
+    /// To convert the first route, or track, of a GPX file
+    Gpx {
+        /// Input files
+        files: Vec<String>,
+
+        /// Mission name, used when the route has none
+        #[clap(short, long)]
+        title: Option<String>,
+    },
+
+    /// To convert a Mission Planner waypoint file (`QGC WPL 110`)
+    Waypoints {
+        /// Input files
+        files: Vec<String>,
 
         /// Mission name
         #[clap(short, long)]
         title: Option<String>,
     },
+
+    /// To convert every mission of a folder and bring the device in line with them
+    Sync {
+        /// Folder holding Litchi missions (KML, CSV, bin), or GeoJSON, GPX and waypoint files
+        dir: PathBuf,
+
+        /// Also delete plans converted by litchi2fp whose mission is no longer in the folder
+        #[clap(long)]
+        prune: bool,
+
+        /// Only show what would be done
+        #[clap(long)]
+        dry_run: bool,
+    },
+
+    /// To convert a Litchi mission (CSV, bin) to the format told by the output's extension:
+    /// `.kmz` for DJI WPML, `.csv`, `.bin` or `.kml` for Litchi
+    Convert {
+        /// Input file
+        file: PathBuf,
+
+        /// Output file
+        output: PathBuf,
+
+        /// Aircraft flying `.kmz` missions
+        #[clap(long, value_enum, default_value_t)]
+        aircraft: Aircraft,
+    },
+
+    /// To draw a plan view of a mission, or plan, without any basemap,
+    /// or to write a report on it
+    Preview {
+        /// Input file, whose format is told by its extension
+        file: PathBuf,
+
+        /// SVG file receiving the drawing
+        #[clap(long, required_unless_present = "html")]
+        svg: Option<PathBuf>,
+
+        /// HTML file receiving the report: plan view, altitude profile, waypoints,
+        /// estimated duration, readable offline
+        #[clap(long)]
+        html: Option<PathBuf>,
+    },
+
+    /// To manage flight plans stored on the device
+    Device {
+        #[clap(subcommand)]
+        command: DeviceCommand,
+    },
 }
 
 fn main() -> Result<(), Error> {
     let args = CommandLineInterface::parse();
 
+    let settings = settings(&args)?;
+
     use Commands::*;
 
-    let output = match &args.command {
-        Kml { file: None, .. } | Csv { file: None, .. } | Bin { file: None, .. } => {
+    let outputs = match &args.command {
+        Kml { files, .. }
+        | Csv { files, .. }
+        | Bin { files, .. }
+        | Geojson { files, .. }
+        | Gpx { files, .. }
+        | Waypoints { files, .. }
+            if files.is_empty() =>
+        {
             CommandLineInterface::command()
                 .error(ErrorKind::MissingRequiredArgument, "FILE is required")
                 .exit()
         }
 
-        Csv {
-            file: Some(path),
-            title,
-        } => from_csv(path, title.as_deref()),
+        Csv { files, title }
+        | Bin { files, title }
+        | Geojson { files, title }
+        | Gpx { files, title }
+        | Waypoints { files, title }
+            if title.is_some() && files.len() > 1 =>
+        {
+            CommandLineInterface::command()
+                .error(
+                    ErrorKind::ArgumentConflict,
+                    "--title names a single mission, convert one FILE at a time",
+                )
+                .exit()
+        }
+
+        Csv { files, title } => files
+            .iter()
+            .map(|path| from_csv(path, &fs::read(path)?, title.as_deref(), &settings))
+            .collect::<Result<Vec<_>, _>>(),
+
+        Bin { files, title } => files
+            .iter()
+            .map(|path| from_bin(path, &fs::read(path)?, title.as_deref(), &settings))
+            .collect::<Result<Vec<_>, _>>(),
+
+        Geojson { files, title } => files
+            .iter()
+            .map(|path| from_geojson(path, &fs::read(path)?, title.as_deref(), &settings))
+            .collect::<Result<Vec<_>, _>>(),
+
+        Gpx { files, title } => files
+            .iter()
+            .map(|path| {
+                from_gpx(
+                    path,
+                    &fs::read(path)?,
+                    title.as_deref(),
+                    args.takeoff_elevation,
+                    &settings,
+                )
+            })
+            .collect::<Result<Vec<_>, _>>(),
+
+        Waypoints { files, title } => files
+            .iter()
+            .map(|path| from_waypoints(path, &fs::read(path)?, title.as_deref(), &settings))
+            .collect::<Result<Vec<_>, _>>(),
+
+        Kml { files } => files
+            .iter()
+            .map(|path| from_kml(&fs::read(path)?, &settings))
+            .collect::<Result<Vec<_>, _>>(),
+
+        Sync {
+            dir,
+            prune,
+            dry_run,
+        } => {
+            let paths = sync::missions(dir)?;
+
+            // Identified as when stored one by one, for both ways to find the same plans.
+            let flightplans = paths
+                .iter()
+                .map(|path| {
+                    let res = fs::read(path)
+                        .map_err(Error::from)
+                        .and_then(|data| from_file(path, &data, args.takeoff_elevation, &settings));
+
+                    if res.is_err() {
+                        eprintln!("Could not convert {}", path.display());
+                    }
+
+                    res
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let mut store = args.location.open()?;
+
+            return sync::run(store.as_mut(), &flightplans, *prune, *dry_run);
+        }
+
+        Convert {
+            file,
+            output,
+            aircraft,
+        } => {
+            convert::run(file, output, *aircraft)?;
+
+            println!("{}: written to {}", file.display(), output.display());
+
+            return Ok(());
+        }
+
+        Preview { file, svg, html } => {
+            let data = fs::read(file)?;
 
-        Bin {
-            file: Some(path),
-            title,
-        } => from_bin(path, title.as_deref()),
+            let flightplan = from_file(file, &data, args.takeoff_elevation, &settings)?;
 
-        Kml { file: Some(path) } => from_kml(path),
+            if let Some(svg) = svg {
+                fs::write(svg, flightplan.to_svg())?;
+            }
+
+            if let Some(html) = html {
+                let name = file.file_name().and_then(OsStr::to_str).unwrap_or_default();
+
+                fs::write(html, flightplan.to_html(name, &data))?;
+            }
+
+            for path in svg.iter().chain(html) {
+                println!(
+                    "'{}' ({}): written to {}",
+                    flightplan.title,
+                    flightplan.uuid,
+                    path.display()
+                );
+            }
+
+            return Ok(());
+        }
+
+        Device { command } => return device::run(command, &args.location),
     }?;
 
-    if args.store {
-        let mut device = mtp::find_device(None)?;
+    // Refused before anything is stored or uploaded.
+    let destination = args
+        .output
+        .as_deref()
+        .map(Destination::try_from)
+        .transpose()?;
+
+    let mut store = match args.store {
+        true => Some(args.location.open()?),
+        false => None,
+    };
+
+    for output in &outputs {
+        if let Some(host) = &args.to_drone {
+            let items: Vec<MissionItem> = output.into();
 
-        mtp::store_flightplan(&mut device, &output)?;
-    } else {
-        println!("{}", String::from(&output));
+            let mut data = vec![];
+
+            mavlink::wpl::to_writer(&items, mavlink::wpl::PARROT_VERSION, &mut data)?;
+
+            let file_name = format!("{}.mavlink", output.folder_name());
+
+            let path = ftp::upload(host, &args.drone_dir, &file_name, &data)?;
+
+            println!(
+                "'{}' ({}): uploaded to {}:{}",
+                output.title, output.uuid, host, path
+            );
+        } else if let Some(store) = store.as_mut() {
+            let outcome = store::store_flightplan(store.as_mut(), output, args.on_conflict)?;
+
+            println!("'{}' ({}): {}", output.title, output.uuid, outcome);
+        } else if args.output.is_none() {
+            println!("{}", String::from(output));
+        }
+    }
+
+    if let Some(destination) = destination {
+        let paths = destination.write(&outputs, args.takeoff_elevation)?;
+
+        match (paths.as_slice(), outputs.as_slice()) {
+            // Bundles and documents hold every plan.
+            ([path], [_, _, ..]) => {
+                println!("{} plans: written to {}", outputs.len(), path.display())
+            }
+
+            _ => {
+                for (output, path) in outputs.iter().zip(paths) {
+                    println!(
+                        "'{}' ({}): written to {}",
+                        output.title,
+                        output.uuid,
+                        path.display()
+                    );
+                }
+            }
+        }
     }
 
     Ok(())
 }
 
+fn settings(args: &CommandLineInterface) -> Result<Settings, Error> {
+    let mut settings = match &args.profile {
+        Some(path) => {
+            serde_json::from_reader(fs::File::open(path)?).map_err(Error::ProfileParsingFailed)?
+        }
+
+        None => Settings::default(),
+    };
+
+    if let Some(resolution) = args.video_resolution {
+        settings.camera.resolution = resolution;
+    }
+
+    if let Some(fps) = args.fps {
+        settings.camera.fps = fps;
+    }
+
+    if let Some(mode) = args.photo_mode {
+        settings.camera.photo = mode;
+    }
+
+    if let Some(takeoff) = &args.takeoff {
+        settings.takeoff = Some(takeoff.clone());
+    }
+
+    if let Some(zoom) = args.zoom {
+        settings.viewport.zoom = Some(zoom);
+    }
+
+    if let Some(center) = args.center {
+        settings.viewport.center = Some(center);
+    }
+
+    if args.random_uuid {
+        settings.uuid = UuidMode::Random;
+    }
+
+    if let Some(tolerance) = args.poi_tolerance {
+        settings.poi_tolerance = tolerance;
+    }
+
+    Ok(settings)
+}
+
+/// Converts a mission, whose format is told by its extension, from the `data` read at `path`.
+fn from_file<'f>(
+    path: &'f PathBuf,
+    data: &[u8],
+    takeoff_elevation: Option<f64>,
+    settings: &Settings,
+) -> Result<FlightPlan<'f>, Error> {
+    let extension = path.extension().and_then(OsStr::to_str).unwrap_or_default();
+
+    match extension.to_ascii_lowercase().as_str() {
+        "kml" => from_kml(data, settings),
+        "bin" => from_bin(path, data, None, settings),
+        "geojson" => from_geojson(path, data, None, settings),
+        "gpx" => from_gpx(path, data, None, takeoff_elevation, settings),
+        "waypoints" => from_waypoints(path, data, None, settings),
+        _ => from_csv(path, data, None, settings),
+    }
+}
+
 fn from_csv<'f, P: AsRef<Path> + 'f>(
     path: &'f P,
+    data: &[u8],
     title: Option<&str>,
+    settings: &Settings,
 ) -> Result<FlightPlan<'f>, Error> {
-    let file = fs::File::open(path)?;
-
     let stem = path.as_ref().file_stem().and_then(OsStr::to_str);
 
     let title = title.or(stem).unwrap_or_else(|| {
@@ -106,29 +499,90 @@ fn from_csv<'f, P: AsRef<Path> + 'f>(
     });
 
     let records: Result<Vec<MissionRecord>, _> =
-        csv::Reader::from_reader(file).deserialize().collect();
+        csv::Reader::from_reader(data).deserialize().collect();
 
     let records = records?;
 
-    let fp = flightplan::from_csv(title, records.as_slice())?;
+    let fp = flightplan::from_csv(title, records.as_slice(), data, settings)?;
 
     Ok(fp)
 }
 
-fn from_kml<'f, P: AsRef<Path> + 'f>(path: P) -> Result<FlightPlan<'f>, Error> {
-    let kml = KmlReader::<_, f64>::from_path(path)?.read()?;
+fn from_kml<'f>(data: &[u8], settings: &Settings) -> Result<FlightPlan<'f>, Error> {
+    let data = std::str::from_utf8(data)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+
+    let kml = KmlReader::<_, f64>::from_string(data).read()?;
 
     let mission = &Mission::try_from(&kml)?;
 
-    flightplan::from_kml(mission)
+    flightplan::from_kml(mission, data.as_bytes(), settings)
 }
 
 fn from_bin<'f, P: AsRef<Path> + 'f>(
     path: &'f P,
+    data: &[u8],
+    title: Option<&str>,
+    settings: &Settings,
+) -> Result<FlightPlan<'f>, Error> {
+    let stem = path.as_ref().file_stem().and_then(OsStr::to_str);
+
+    let title = title.or(stem).unwrap_or_else(|| {
+        CommandLineInterface::command()
+            .error(ErrorKind::MissingRequiredArgument, "a title is required")
+            .exit()
+    });
+
+    let mission = &litchi::bin::from_slice(data)?;
+
+    flightplan::from_bin(title, mission, data, settings)
+}
+
+fn from_geojson<'f, P: AsRef<Path> + 'f>(
+    path: &'f P,
+    data: &[u8],
+    title: Option<&str>,
+    settings: &Settings,
+) -> Result<FlightPlan<'f>, Error> {
+    let stem = path.as_ref().file_stem().and_then(OsStr::to_str);
+
+    let collection: FeatureCollection =
+        serde_json::from_slice(data).map_err(Error::GeoJsonParsingFailed)?;
+
+    let title = title
+        .or(collection.name.as_deref())
+        .or(stem)
+        .unwrap_or_default();
+
+    flightplan::from_geojson(title, &collection, data, settings)
+}
+
+/// Elevations are relative to `takeoff_elevation`, the first one of the path by default.
+fn from_gpx<'f, P: AsRef<Path> + 'f>(
+    path: &'f P,
+    data: &[u8],
     title: Option<&str>,
+    takeoff_elevation: Option<f64>,
+    settings: &Settings,
 ) -> Result<FlightPlan<'f>, Error> {
-    let mut file = fs::File::open(path)?;
+    let stem = path.as_ref().file_stem().and_then(OsStr::to_str);
+
+    let mut gpx = Gpx::from_slice(data)?;
 
+    gpx.takeoff_elevation = takeoff_elevation;
+
+    let title = title.or(gpx.name.as_deref()).or(stem).unwrap_or_default();
+
+    flightplan::from_gpx(title, &gpx, data, settings)
+}
+
+/// Also reports, on the standard error, what couldn't be converted as is.
+fn from_waypoints<'f, P: AsRef<Path> + 'f>(
+    path: &'f P,
+    data: &[u8],
+    title: Option<&str>,
+    settings: &Settings,
+) -> Result<FlightPlan<'f>, Error> {
     let stem = path.as_ref().file_stem().and_then(OsStr::to_str);
 
     let title = title.or(stem).unwrap_or_else(|| {
@@ -137,11 +591,13 @@ fn from_bin<'f, P: AsRef<Path> + 'f>(
             .exit()
     });
 
-    let mut data = vec![];
+    let items = mavlink::wpl::from_reader(data)?;
 
-    file.read_to_end(&mut data)?;
+    let (flightplan, losses) = flightplan::from_waypoints(title, &items, data, settings)?;
 
-    let mission = &litchi::bin::from_slice(&data)?;
+    for loss in losses {
+        eprintln!("'{}': {}", title, loss);
+    }
 
-    flightplan::from_bin(title, mission)
+    Ok(flightplan)
 }
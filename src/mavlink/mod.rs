@@ -0,0 +1,157 @@
+//! MAVLink mission items, as exchanged in `QGC WPL` waypoint files.
+
+pub mod qgc;
+pub mod wpl;
+
+/// `MAV_FRAME` values used by mission items.
+pub mod frame {
+    /// Altitude above sea level
+    pub const GLOBAL: u8 = 0;
+
+    /// Not a position: the item is a command
+    pub const MISSION: u8 = 2;
+
+    /// Altitude relative to the home position
+    pub const GLOBAL_RELATIVE_ALT: u8 = 3;
+
+    pub const GLOBAL_INT: u8 = 5;
+    pub const GLOBAL_RELATIVE_ALT_INT: u8 = 6;
+
+    /// Altitude above the terrain below
+    pub const GLOBAL_TERRAIN_ALT: u8 = 10;
+
+    pub const GLOBAL_TERRAIN_ALT_INT: u8 = 11;
+}
+
+/// `MAV_CMD` values used by mission items.
+pub mod command {
+    pub const NAV_WAYPOINT: u16 = 16;
+    pub const NAV_LOITER_UNLIM: u16 = 17;
+    pub const NAV_LOITER_TURNS: u16 = 18;
+    pub const NAV_LOITER_TIME: u16 = 19;
+    pub const NAV_RETURN_TO_LAUNCH: u16 = 20;
+    pub const NAV_LAND: u16 = 21;
+    pub const NAV_TAKEOFF: u16 = 22;
+    pub const NAV_SPLINE_WAYPOINT: u16 = 82;
+    pub const NAV_DELAY: u16 = 93;
+    pub const CONDITION_YAW: u16 = 115;
+    pub const DO_JUMP: u16 = 177;
+    pub const DO_CHANGE_SPEED: u16 = 178;
+    pub const DO_SET_ROI_LOCATION: u16 = 195;
+    pub const DO_SET_ROI_NONE: u16 = 197;
+    pub const DO_SET_ROI: u16 = 201;
+    pub const DO_DIGICAM_CONTROL: u16 = 203;
+    pub const DO_MOUNT_CONTROL: u16 = 205;
+    pub const DO_SET_CAM_TRIGG_DIST: u16 = 206;
+    pub const IMAGE_START_CAPTURE: u16 = 2000;
+    pub const IMAGE_STOP_CAPTURE: u16 = 2001;
+    pub const VIDEO_START_CAPTURE: u16 = 2500;
+    pub const VIDEO_STOP_CAPTURE: u16 = 2501;
+
+    /// Name of a command, without its `MAV_CMD_` prefix.
+    pub fn name(command: u16) -> Option<&'static str> {
+        let name = match command {
+            NAV_WAYPOINT => "NAV_WAYPOINT",
+            NAV_LOITER_UNLIM => "NAV_LOITER_UNLIM",
+            NAV_LOITER_TURNS => "NAV_LOITER_TURNS",
+            NAV_LOITER_TIME => "NAV_LOITER_TIME",
+            NAV_RETURN_TO_LAUNCH => "NAV_RETURN_TO_LAUNCH",
+            NAV_LAND => "NAV_LAND",
+            NAV_TAKEOFF => "NAV_TAKEOFF",
+            NAV_SPLINE_WAYPOINT => "NAV_SPLINE_WAYPOINT",
+            NAV_DELAY => "NAV_DELAY",
+            CONDITION_YAW => "CONDITION_YAW",
+            DO_JUMP => "DO_JUMP",
+            DO_CHANGE_SPEED => "DO_CHANGE_SPEED",
+            DO_SET_ROI_LOCATION => "DO_SET_ROI_LOCATION",
+            DO_SET_ROI_NONE => "DO_SET_ROI_NONE",
+            DO_SET_ROI => "DO_SET_ROI",
+            DO_DIGICAM_CONTROL => "DO_DIGICAM_CONTROL",
+            DO_MOUNT_CONTROL => "DO_MOUNT_CONTROL",
+            DO_SET_CAM_TRIGG_DIST => "DO_SET_CAM_TRIGG_DIST",
+            IMAGE_START_CAPTURE => "IMAGE_START_CAPTURE",
+            IMAGE_STOP_CAPTURE => "IMAGE_STOP_CAPTURE",
+            VIDEO_START_CAPTURE => "VIDEO_START_CAPTURE",
+            VIDEO_STOP_CAPTURE => "VIDEO_STOP_CAPTURE",
+
+            _ => return None,
+        };
+
+        Some(name)
+    }
+}
+
+/// `MAV_ROI_LOCATION`, for `DO_SET_ROI`
+pub const ROI_LOCATION: f64 = 3.0;
+
+/// `MAV_ROI_NONE`, for `DO_SET_ROI`
+pub const ROI_NONE: f64 = 0.0;
+
+/// `MAV_MOUNT_MODE_MAVLINK_TARGETING`, for `DO_MOUNT_CONTROL`
+pub const MOUNT_MODE_MAVLINK_TARGETING: f64 = 2.0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MissionItem {
+    pub frame: u8,
+    pub command: u16,
+
+    /// Command specific parameters 1 to 4
+    pub params: [f64; 4],
+
+    /// Latitude, or parameter 5
+    pub x: f64,
+
+    /// Longitude, or parameter 6
+    pub y: f64,
+
+    /// Altitude, or parameter 7
+    pub z: f64,
+
+    pub autocontinue: bool,
+}
+
+impl MissionItem {
+    /// A position above sea level, as Mission Planner writes the home position.
+    pub fn home(latitude: f64, longitude: f64, altitude: f64) -> Self {
+        MissionItem {
+            frame: frame::GLOBAL,
+            ..MissionItem::at(
+                command::NAV_WAYPOINT,
+                [0.0; 4],
+                latitude,
+                longitude,
+                altitude,
+            )
+        }
+    }
+
+    /// A command without position.
+    pub fn command(command: u16, params: [f64; 4]) -> Self {
+        MissionItem {
+            frame: frame::MISSION,
+            command,
+            params,
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+            autocontinue: true,
+        }
+    }
+
+    /// A command at a position, its altitude relative to the home position.
+    pub fn at(
+        command: u16,
+        params: [f64; 4],
+        latitude: f64,
+        longitude: f64,
+        altitude: f64,
+    ) -> Self {
+        MissionItem {
+            frame: frame::GLOBAL_RELATIVE_ALT,
+            x: latitude,
+            y: longitude,
+            z: altitude,
+            ..MissionItem::command(command, params)
+        }
+    }
+}
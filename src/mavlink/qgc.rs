@@ -0,0 +1,82 @@
+//! QGroundControl `.plan` files: a JSON document holding the mission,
+//! the geofence and the rally points.
+
+use serde_json::{json, Value};
+
+use super::{frame, MissionItem};
+
+/// `MAV_AUTOPILOT_GENERIC`: QGroundControl adapts the plan to the connected vehicle.
+const FIRMWARE_GENERIC: u8 = 0;
+
+/// `MAV_TYPE_QUADROTOR`
+const VEHICLE_QUADROTOR: u8 = 2;
+
+/// QGroundControl's altitude mode for altitudes relative to the home position
+const ALTITUDE_MODE_RELATIVE: u8 = 1;
+
+pub struct Plan {
+    pub items: Vec<MissionItem>,
+
+    /// Latitude, longitude and altitude above sea level of the home position
+    pub home: [f64; 3],
+
+    /// Default speeds in m/s, for items not changing it
+    pub cruise_speed: f64,
+    pub hover_speed: f64,
+}
+
+impl Plan {
+    /// The plan as written by QGroundControl, without geofence nor rally points.
+    /// Parameters not set (NaN) are written as `null`.
+    pub fn to_json(&self) -> Value {
+        let items: Vec<_> = self
+            .items
+            .iter()
+            .enumerate()
+            .map(|(index, item)| {
+                let [p1, p2, p3, p4] = item.params;
+
+                let mut value = json!({
+                    "type": "SimpleItem",
+                    "doJumpId": index + 1,
+                    "command": item.command,
+                    "frame": item.frame,
+                    "params": [p1, p2, p3, p4, item.x, item.y, item.z],
+                    "autoContinue": item.autocontinue,
+                });
+
+                if item.frame == frame::GLOBAL_RELATIVE_ALT {
+                    value["Altitude"] = json!(item.z);
+                    value["AltitudeMode"] = json!(ALTITUDE_MODE_RELATIVE);
+                    value["AMSLAltAboveTerrain"] = Value::Null;
+                }
+
+                value
+            })
+            .collect();
+
+        json!({
+            "fileType": "Plan",
+            "version": 1,
+            "groundStation": "QGroundControl",
+            "mission": {
+                "version": 2,
+                "firmwareType": FIRMWARE_GENERIC,
+                "vehicleType": VEHICLE_QUADROTOR,
+                "cruiseSpeed": self.cruise_speed,
+                "hoverSpeed": self.hover_speed,
+                "plannedHomePosition": self.home,
+                "items": items,
+            },
+            "geoFence": {
+                "version": 2,
+                "circles": [],
+                "polygons": [],
+            },
+            "rallyPoints": {
+                "version": 2,
+                "points": [],
+            },
+        })
+    }
+}
@@ -0,0 +1,146 @@
+//! `QGC WPL <version>` waypoint files: a header line,
+//! then one tab separated line per item:
+//! `seq current frame command p1 p2 p3 p4 x y z autocontinue`.
+
+use std::io::{self, BufRead, Write};
+
+use crate::error::Error;
+
+use super::MissionItem;
+
+/// Version written by Parrot's own tools.
+pub const PARROT_VERSION: u16 = 120;
+
+/// Version written by Mission Planner, whose first item is the home position.
+pub const MISSION_PLANNER_VERSION: u16 = 110;
+
+const HEADER: &str = "QGC WPL ";
+
+/// Reads the items of any version, in the order of the file, whatever their sequence number.
+pub fn from_reader<R: BufRead>(reader: R) -> Result<Vec<MissionItem>, Error> {
+    let mut items = vec![];
+    let mut header = false;
+
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+
+        if line.is_empty() {
+            continue;
+        }
+
+        let malformed = || Error::MalformedWaypointFile(index + 1);
+
+        if !header {
+            if !line.starts_with(HEADER) {
+                return Err(malformed());
+            }
+
+            header = true;
+
+            continue;
+        }
+
+        let fields: Vec<f64> = line
+            .split_whitespace()
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .map_err(|_| malformed())?;
+
+        match fields[..] {
+            // Unset parameters are NaN, but the frame and command can't be.
+            [_, _, frame, command, p1, p2, p3, p4, x, y, z, autocontinue]
+                if frame.is_finite() && command.is_finite() && autocontinue.is_finite() =>
+            {
+                items.push(MissionItem {
+                    frame: frame as u8,
+                    command: command as u16,
+                    params: [p1, p2, p3, p4],
+                    x,
+                    y,
+                    z,
+                    autocontinue: autocontinue != 0.0,
+                })
+            }
+
+            _ => return Err(malformed()),
+        }
+    }
+
+    Ok(items)
+}
+
+pub fn to_writer<W: Write>(items: &[MissionItem], version: u16, mut writer: W) -> io::Result<()> {
+    writeln!(writer, "{}{}", HEADER, version)?;
+
+    for (seq, item) in items.iter().enumerate() {
+        let [p1, p2, p3, p4] = item.params;
+
+        writeln!(
+            writer,
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            seq,
+            u8::from(seq == 0),
+            item.frame,
+            item.command,
+            p1,
+            p2,
+            p3,
+            p4,
+            item.x,
+            item.y,
+            item.z,
+            u8::from(item.autocontinue)
+        )?;
+    }
+
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{error::Error, mavlink::command::*};
+
+    use super::from_reader;
+
+    #[test]
+    fn items_are_read_in_the_order_of_the_file() {
+        let file = "QGC WPL 120\r
+\r
+1\t0\t3\t2000\t0\t5\t0\tnan\t0\t0\t0\t1\r
+0 1 0 16 0 0 0 0 48.8583 2.2945 100 1\r
+";
+
+        let items = from_reader(file.as_bytes()).unwrap();
+
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].command, IMAGE_START_CAPTURE);
+        assert_eq!(items[0].params[1], 5.0);
+        assert!(items[0].params[3].is_nan());
+        assert!(items[0].autocontinue);
+        assert_eq!(items[1].command, NAV_WAYPOINT);
+        assert_eq!(
+            (items[1].x, items[1].y, items[1].z),
+            (48.8583, 2.2945, 100.0)
+        );
+    }
+
+    #[test]
+    fn malformed_lines_are_reported() {
+        let line = |file: &str| match from_reader(file.as_bytes()) {
+            Err(Error::MalformedWaypointFile(line)) => line,
+            res => panic!("{:?} read", res.map(|items| items.len())),
+        };
+
+        assert_eq!(line("0\t1\t0\t16\t0\t0\t0\t0\t0\t0\t0\t1\n"), 1);
+        assert_eq!(line("QGC WPL 110\n0\t1\t0\t16\t0\t0\t0\t0\t0\t0\t0\n"), 2);
+        assert_eq!(
+            line("QGC WPL 110\n\n0\t1\t0\twp\t0\t0\t0\t0\t0\t0\t0\t1\n"),
+            3
+        );
+        assert_eq!(
+            line("QGC WPL 110\n0\t1\t0\tnan\t0\t0\t0\t0\t0\t0\t0\t1\n"),
+            2
+        );
+    }
+}
@@ -1,6 +1,7 @@
-use std::str::FromStr;
+use std::{fmt::Display, str::FromStr};
+
+use chrono::{DateTime, TimeZone, Utc};
 
-use chrono::TimeZone;
 use libmtp_rs::{
     device::{raw::RawDevice, MtpDevice, StorageSort},
     internals::DeviceEntry,
@@ -12,7 +13,13 @@ use libmtp_rs::{
     util::HandlerReturn,
 };
 
-use crate::{error::Error, flightplan::FlightPlan};
+use crate::{
+    error::Error,
+    flightplan::FlightPlan,
+    store::{
+        FlightPlanStore, FLIGHTPLAN_FOLDER_NAME, FREEFLIGHT6_FOLDER_NAME, SAVED_PLAN_FILE_NAME,
+    },
+};
 
 #[derive(Debug)]
 pub enum MtpError {
@@ -22,6 +29,17 @@ pub enum MtpError {
     UnableToOpenDevice,
     NoFreeFlight6Folder,
     NoStorage,
+    NoSuchStorage(String),
+
+    /// More than one storage holds a `FreeFlight 6` folder
+    AmbiguousStorage(Vec<String>),
+
+    /// An uploaded file still didn't read back as sent after every retry
+    UploadMismatch {
+        file_name: String,
+        sent: usize,
+        received: usize,
+    },
 }
 
 impl From<libmtp_rs::error::Error> for MtpError {
@@ -30,157 +48,499 @@ impl From<libmtp_rs::error::Error> for MtpError {
     }
 }
 
-struct DeviceId(u16, u16);
+/// Selects a device by its USB ids, written in hex as `<vendor>:<product>`, or by its serial number.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum DeviceId {
+    Usb(u16, u16),
+    Serial(String),
+}
 
 impl FromStr for DeviceId {
     type Err = MtpError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if let Some((vendor, product)) = s.split_once(':') {
-            return match (u16::from_str(vendor), u16::from_str(product)) {
-                (Ok(v), Ok(p)) => Ok(DeviceId(v, p)),
+        let hex = |id: &str| u16::from_str_radix(id.trim().trim_start_matches("0x"), 16);
+
+        match s.split_once(':') {
+            Some((vendor, product)) => match (hex(vendor), hex(product)) {
+                (Ok(v), Ok(p)) => Ok(DeviceId::Usb(v, p)),
 
                 _ => Err(MtpError::MalformedDeviceId),
-            };
-        }
+            },
+
+            None if !s.trim().is_empty() => Ok(DeviceId::Serial(s.trim().to_owned())),
 
-        Err(MtpError::MalformedDeviceId)
+            None => Err(MtpError::MalformedDeviceId),
+        }
     }
 }
 
-fn find_raw_device(device_id: Option<&str>) -> Result<RawDevice, MtpError> {
-    let mut devices = libmtp_rs::device::raw::detect_raw_devices()?.into_iter();
+/// A device found on the USB bus.
+pub struct DetectedDevice {
+    pub bus_number: u32,
+    pub dev_number: u8,
+    pub entry: DeviceEntry,
+
+    /// Only known when the device could be opened
+    pub serial_number: Option<String>,
+}
+
+/// Lists the MTP devices currently plugged in.
+pub fn detect_devices() -> Result<Vec<DetectedDevice>, Error> {
+    let devices = libmtp_rs::device::raw::detect_raw_devices().map_err(MtpError::from)?;
+
+    let detected = devices
+        .iter()
+        .map(|device| DetectedDevice {
+            bus_number: device.bus_number(),
+            dev_number: device.dev_number(),
+            entry: device.device_entry(),
+            serial_number: device
+                .open_uncached()
+                .and_then(|opened| opened.serial_number().ok()),
+        })
+        .collect();
+
+    Ok(detected)
+}
+
+fn find_folder_by_name<'a, 'f>(name: &'_ str, files: &'f [File<'a>]) -> Option<&'f File<'a>> {
+    files.iter().find(|&file| file.name() == name)
+}
+
+/// Opens the device selected by `device_id`, see [`DeviceId`], or the first one found.
+pub fn find_device(device_id: Option<&str>) -> Result<MtpDevice, Error> {
+    let devices = libmtp_rs::device::raw::detect_raw_devices().map_err(MtpError::from)?;
+
+    let open = |device: &RawDevice| device.open_uncached().ok_or(MtpError::UnableToOpenDevice);
 
     let device_id = device_id.map(DeviceId::from_str).transpose()?;
 
-    match device_id {
-        Some(DeviceId(vendor, product)) => {
-            for device in devices {
+    let device = match device_id {
+        Some(DeviceId::Usb(vendor, product)) => {
+            let device = devices.iter().find(|device| {
                 let DeviceEntry {
                     vendor_id,
                     product_id,
                     ..
                 } = device.device_entry();
 
-                if vendor_id == vendor && product_id == product {
-                    return Ok(device);
-                }
-            }
+                vendor_id == vendor && product_id == product
+            });
 
-            Err(MtpError::NoMtpDeviceDetected)
+            open(device.ok_or(MtpError::NoMtpDeviceDetected)?)?
         }
 
-        None => devices.next().ok_or(MtpError::NoMtpDeviceDetected),
-    }
+        Some(DeviceId::Serial(serial)) => devices
+            .iter()
+            .filter_map(RawDevice::open_uncached)
+            .find(|device| device.serial_number().is_ok_and(|s| s == serial))
+            .ok_or(MtpError::NoMtpDeviceDetected)?,
+
+        None => open(devices.first().ok_or(MtpError::NoMtpDeviceDetected)?)?,
+    };
+
+    Ok(device)
 }
 
-fn find_folder_by_name<'a, 'f>(name: &'_ str, files: &'f [File<'a>]) -> Option<&'f File<'a>> {
-    files.iter().find(|&file| file.name() == name)
+/// A storage of the device, e.g. the internal memory or an SD card.
+pub struct StorageInfo {
+    pub id: u32,
+    pub description: String,
+
+    /// Id of the `FreeFlight 6` folder at its root, if any
+    ff6_folder_id: Option<u32>,
+}
+
+impl Display for StorageInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "'{}' ({:#010x})", self.description, self.id)
+    }
 }
 
-pub fn find_device(device: Option<&str>) -> Result<MtpDevice, Error> {
-    find_raw_device(device)?
-        .open_uncached()
-        .ok_or_else(|| Error::from(MtpError::UnableToOpenDevice))
+impl StorageInfo {
+    /// Whether `selector` is this storage's id, in decimal or `0x` prefixed hex, or description.
+    fn matches(&self, selector: &str) -> bool {
+        let id = match selector.strip_prefix("0x") {
+            Some(hex) => u32::from_str_radix(hex, 16).ok(),
+            None => selector.parse().ok(),
+        };
+
+        id == Some(self.id) || self.description.eq_ignore_ascii_case(selector)
+    }
 }
 
-pub fn find_some_folder(
-    device: &mut MtpDevice,
-    parent: Parent,
-    name: &str,
-) -> Result<Option<u32>, Error> {
+/// Lists the storages of the device, looking for FreeFlight's folder in each of them.
+pub fn list_storages(device: &mut MtpDevice) -> Result<Vec<StorageInfo>, Error> {
     device
         .update_storage(StorageSort::NotSorted)
         .map_err(MtpError::InternalFailure)?;
 
-    if let Some((_, storage)) = device.storage_pool().iter().next() {
-        let root = storage.files_and_folders(parent);
+    let pool = device.storage_pool();
 
-        let folder = find_folder_by_name(name, root.as_slice()).map(File::id);
+    let storages = pool
+        .iter()
+        .map(|(id, storage)| {
+            let root = storage.files_and_folders(Parent::Root);
 
-        return Ok(folder);
-    }
-
-    Ok(None)
-}
+            StorageInfo {
+                id,
+                description: storage.description().unwrap_or_default().to_owned(),
+                ff6_folder_id: find_folder_by_name(FREEFLIGHT6_FOLDER_NAME, &root).map(File::id),
+            }
+        })
+        .collect();
 
-pub fn find_freeflight6_folder(device: &mut MtpDevice) -> Result<u32, Error> {
-    find_some_folder(device, Parent::Root, "FreeFlight 6")?
-        .ok_or_else(|| Error::from(MtpError::NoFreeFlight6Folder))
+    Ok(storages)
 }
 
-fn find_flightplan_folder(
-    device: &mut MtpDevice,
+/// FreeFlight's tree on one storage of the device, as seen through MTP.
+pub struct MtpStore {
+    device: MtpDevice,
+    storage_id: u32,
     ff6_folder_id: u32,
-) -> Result<Option<u32>, Error> {
-    find_some_folder(device, Parent::Folder(ff6_folder_id), "flightPlan")
+
+    /// How many times an upload that doesn't read back, or not as sent, is tried again
+    upload_retries: u32,
 }
 
-fn create_folder(device: &mut MtpDevice, parent: Parent, name: &str) -> Result<u32, Error> {
-    device
-        .update_storage(StorageSort::NotSorted)
-        .map_err(MtpError::InternalFailure)?;
+pub const DEFAULT_UPLOAD_RETRIES: u32 = 2;
+
+impl MtpStore {
+    /// Uses the storage matching `storage` if given, see [`StorageInfo::matches`],
+    /// or else the only one holding a `FreeFlight 6` folder.
+    pub fn open(mut device: MtpDevice, storage: Option<&str>) -> Result<Self, Error> {
+        let storages = list_storages(&mut device)?;
+
+        let storage = match storage {
+            Some(selector) => storages
+                .into_iter()
+                .find(|s| s.matches(selector))
+                .ok_or_else(|| MtpError::NoSuchStorage(selector.to_owned()))?,
+
+            None => {
+                let mut candidates: Vec<_> = storages
+                    .into_iter()
+                    .filter(|s| s.ff6_folder_id.is_some())
+                    .collect();
+
+                match candidates.len() {
+                    0 => return Err(MtpError::NoFreeFlight6Folder.into()),
+                    1 => candidates.remove(0),
+                    _ => {
+                        let names = candidates.iter().map(StorageInfo::to_string).collect();
+
+                        return Err(MtpError::AmbiguousStorage(names).into());
+                    }
+                }
+            }
+        };
 
-    let pool = device.storage_pool();
+        let ff6_folder_id = storage.ff6_folder_id.ok_or(MtpError::NoFreeFlight6Folder)?;
 
-    let (_, storage) = pool.iter().next().ok_or(MtpError::NoStorage)?;
+        eprintln!("Using FreeFlight data on storage {}", storage);
 
-    let (folder_id, _) = storage
-        .create_folder(name, parent)
-        .map_err(MtpError::InternalFailure)?;
+        Ok(MtpStore {
+            device,
+            storage_id: storage.id,
+            ff6_folder_id,
+            upload_retries: DEFAULT_UPLOAD_RETRIES,
+        })
+    }
 
-    Ok(folder_id)
-}
+    pub fn with_upload_retries(self, upload_retries: u32) -> Self {
+        MtpStore {
+            upload_retries,
+            ..self
+        }
+    }
 
-pub fn store_flightplan(device: &mut MtpDevice, flightplan: &FlightPlan) -> Result<(), Error> {
-    let ff6_folder_id = find_freeflight6_folder(device)?;
+    /// Ids, names and whether it is a folder, of everything directly under `parent`.
+    fn list_content(&mut self, parent: Parent) -> Result<Vec<(u32, String, bool)>, Error> {
+        self.device
+            .update_storage(StorageSort::NotSorted)
+            .map_err(MtpError::InternalFailure)?;
 
-    let fp_folder_id = match find_flightplan_folder(device, ff6_folder_id)? {
-        Some(id) => id,
-        None => create_folder(device, Parent::Folder(ff6_folder_id), "flightPlan")?,
-    };
+        let pool = self.device.storage_pool();
+
+        let storage = pool.by_id(self.storage_id).ok_or(MtpError::NoStorage)?;
+
+        let content = storage
+            .files_and_folders(parent)
+            .iter()
+            .map(|file| {
+                let is_folder = matches!(file.ftype(), Filetype::Folder);
+
+                (file.id(), file.name().to_owned(), is_folder)
+            })
+            .collect();
+
+        Ok(content)
+    }
 
-    let dest = create_folder(device, Parent::Folder(fp_folder_id), &flightplan.uuid)?;
+    fn find_some_folder(&mut self, parent: Parent, name: &str) -> Result<Option<u32>, Error> {
+        let content = self.list_content(parent)?;
 
-    store_flightplan_at(device, dest, flightplan)
+        Ok(content
+            .into_iter()
+            .find(|(_, file_name, _)| file_name == name)
+            .map(|(id, _, _)| id))
+    }
+
+    fn find_flightplan_folder(&mut self) -> Result<Option<u32>, Error> {
+        self.find_some_folder(Parent::Folder(self.ff6_folder_id), FLIGHTPLAN_FOLDER_NAME)
+    }
+
+    /// Id of the folder named `folder` under `FreeFlight 6/flightPlan`.
+    fn find_plan_folder(&mut self, folder: &str) -> Result<Option<u32>, Error> {
+        match self.find_flightplan_folder()? {
+            Some(id) => self.find_some_folder(Parent::Folder(id), folder),
+            None => Ok(None),
+        }
+    }
+
+    fn create_folder(&mut self, parent: Parent, name: &str) -> Result<u32, Error> {
+        self.device
+            .update_storage(StorageSort::NotSorted)
+            .map_err(MtpError::InternalFailure)?;
+
+        let pool = self.device.storage_pool();
+
+        let storage = pool.by_id(self.storage_id).ok_or(MtpError::NoStorage)?;
+
+        let (folder_id, _) = storage
+            .create_folder(name, parent)
+            .map_err(MtpError::InternalFailure)?;
+
+        Ok(folder_id)
+    }
+
+    fn delete_object(&self, id: u32) -> Result<(), Error> {
+        self.device
+            .dummy_object(id)
+            .delete()
+            .map_err(MtpError::InternalFailure)?;
+
+        Ok(())
+    }
+
+    fn download_file(&self, file_id: u32) -> Result<Vec<u8>, Error> {
+        let mut buffer = vec![];
+
+        let handler = |data: &[u8]| {
+            buffer.extend_from_slice(data);
+
+            HandlerReturn::Ok(data.len() as u32)
+        };
+
+        self.device
+            .storage_pool()
+            .get_file_to_handler(file_id, handler)
+            .map_err(MtpError::InternalFailure)?;
+
+        Ok(buffer)
+    }
+
+    /// Uploads `data` as a new file in `parent`, then reads it back to make sure it arrived whole.
+    /// A mismatching or unreadable file is deleted, then uploaded again up to `upload_retries` times.
+    fn upload_verified(
+        &self,
+        parent: u32,
+        name: &str,
+        data: &[u8],
+        modification_date: DateTime<Utc>,
+    ) -> Result<(), Error> {
+        let mut attempt = 0;
+
+        loop {
+            let file_id = self.upload_file(parent, name, data, modification_date)?;
+
+            // Failing to read back usually means a truncated or missing object: retried too.
+            let received = self.download_file(file_id);
+
+            if received.as_deref().is_ok_and(|received| received == data) {
+                return Ok(());
+            }
+
+            let deleted = self.delete_object(file_id);
+
+            // A missing object can't be deleted either.
+            if received.is_ok() {
+                deleted?;
+            }
+
+            if attempt == self.upload_retries {
+                let received = received?;
+
+                return Err(MtpError::UploadMismatch {
+                    file_name: name.to_owned(),
+                    sent: data.len(),
+                    received: received.len(),
+                }
+                .into());
+            }
+
+            attempt += 1;
+
+            match received {
+                Ok(received) => eprintln!(
+                    "{} did not read back as sent ({} of {} bytes), retrying ({}/{})",
+                    name,
+                    received.len(),
+                    data.len(),
+                    attempt,
+                    self.upload_retries
+                ),
+
+                Err(e) => eprintln!(
+                    "{} could not be read back ({:?}), retrying ({}/{})",
+                    name, e, attempt, self.upload_retries
+                ),
+            }
+        }
+    }
+
+    /// Uploads `data` as a new file in `parent`, returns its id.
+    fn upload_file(
+        &self,
+        parent: u32,
+        name: &str,
+        data: &[u8],
+        modification_date: DateTime<Utc>,
+    ) -> Result<u32, Error> {
+        let mut remaining = data;
+
+        let metadata = FileMetadata {
+            file_size: data.len() as u64,
+            file_name: name,
+            file_type: Filetype::Text,
+            modification_date,
+        };
+
+        let handler = |mut buffer: &mut [u8]| {
+            use std::io::Write;
+
+            match buffer.write(remaining) {
+                Ok(n) => {
+                    remaining = &remaining[n..];
+
+                    HandlerReturn::Ok(n as u32)
+                }
+                Err(_) => HandlerReturn::Error,
+            }
+        };
+
+        let pool = self.device.storage_pool();
+
+        let storage = pool.by_id(self.storage_id).ok_or(MtpError::NoStorage)?;
+
+        let file = storage
+            .send_file_from_handler(handler, Parent::Folder(parent), metadata)
+            .map_err(MtpError::InternalFailure)?;
+
+        Ok(file.id())
+    }
 }
 
-pub fn store_flightplan_at(
-    device: &mut MtpDevice,
-    dest_folder_id: u32,
-    flightplan: &FlightPlan,
-) -> Result<(), Error> {
-    let modification_date = chrono::Utc
-        .timestamp_millis_opt(flightplan.date as i64)
-        .single()
-        .unwrap_or_default();
+impl FlightPlanStore for MtpStore {
+    fn list(&mut self) -> Result<Vec<String>, Error> {
+        let fp_folder_id = match self.find_flightplan_folder()? {
+            Some(id) => id,
+            None => return Ok(vec![]),
+        };
+
+        let folders = self
+            .list_content(Parent::Folder(fp_folder_id))?
+            .into_iter()
+            .filter(|(_, _, is_folder)| *is_folder)
+            .map(|(_, name, _)| name)
+            .collect();
+
+        Ok(folders)
+    }
 
-    let mut buffer: Vec<u8> = flightplan.into();
+    fn read(&mut self, folder: &str) -> Result<Option<Vec<u8>>, Error> {
+        let file_id = match self.find_plan_folder(folder)? {
+            Some(id) => self.find_some_folder(Parent::Folder(id), SAVED_PLAN_FILE_NAME)?,
+            None => None,
+        };
 
-    let metadata = FileMetadata {
-        file_size: buffer.len() as u64,
-        file_name: "savedPlan.json",
-        file_type: Filetype::Text,
-        modification_date,
-    };
+        file_id.map(|id| self.download_file(id)).transpose()
+    }
 
-    let handler = |mut data: &mut [u8]| {
-        use std::io::Write;
+    fn exists(&mut self, folder: &str) -> Result<bool, Error> {
+        let file_id = match self.find_plan_folder(folder)? {
+            Some(id) => self.find_some_folder(Parent::Folder(id), SAVED_PLAN_FILE_NAME)?,
+            None => None,
+        };
 
-        match data.write(buffer.as_slice()) {
-            Ok(n) => {
-                buffer.drain(0..n);
+        Ok(file_id.is_some())
+    }
 
-                HandlerReturn::Ok(n as u32)
+    fn write(&mut self, folder: &str, flightplan: &FlightPlan) -> Result<(), Error> {
+        let fp_folder_id = match self.find_flightplan_folder()? {
+            Some(id) => id,
+            None => {
+                self.create_folder(Parent::Folder(self.ff6_folder_id), FLIGHTPLAN_FOLDER_NAME)?
             }
-            Err(_) => HandlerReturn::Error,
+        };
+
+        let dest = match self.find_some_folder(Parent::Folder(fp_folder_id), folder)? {
+            Some(id) => id,
+            None => self.create_folder(Parent::Folder(fp_folder_id), folder)?,
+        };
+
+        if let Some(file_id) = self.find_some_folder(Parent::Folder(dest), SAVED_PLAN_FILE_NAME)? {
+            self.delete_object(file_id)?;
         }
-    };
 
-    let pool = device.storage_pool();
+        // The file is dated as the plan, not as the upload.
+        let modification_date = Utc
+            .timestamp_millis_opt(flightplan.date as i64)
+            .single()
+            .unwrap_or_default();
+
+        self.upload_verified(
+            dest,
+            SAVED_PLAN_FILE_NAME,
+            &Vec::from(flightplan),
+            modification_date,
+        )
+    }
 
-    pool.send_file_from_handler(handler, Parent::Folder(dest_folder_id), metadata)
-        .map_err(MtpError::InternalFailure)
-        .map_err(Error::from)
-        .and(Ok(()))
+    fn delete(&mut self, folder: &str) -> Result<(), Error> {
+        let folder_id = self
+            .find_plan_folder(folder)?
+            .ok_or_else(|| Error::FlightPlanNotFound(folder.to_owned()))?;
+
+        for (id, _, _) in self.list_content(Parent::Folder(folder_id))? {
+            self.delete_object(id)?;
+        }
+
+        self.delete_object(folder_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DeviceId;
+
+    #[test]
+    fn device_ids_are_hex_or_serial() {
+        assert_eq!(
+            "19f0:0001".parse::<DeviceId>().ok(),
+            Some(DeviceId::Usb(0x19f0, 0x0001))
+        );
+        assert_eq!(
+            "0x18D1:0x4EE1".parse::<DeviceId>().ok(),
+            Some(DeviceId::Usb(0x18d1, 0x4ee1))
+        );
+        assert_eq!(
+            "R52N80ABCDE".parse::<DeviceId>().ok(),
+            Some(DeviceId::Serial("R52N80ABCDE".into()))
+        );
+
+        assert!("18d1:phone".parse::<DeviceId>().is_err());
+        assert!("".parse::<DeviceId>().is_err());
+    }
 }
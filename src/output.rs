@@ -0,0 +1,392 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use kml::{types::KmlDocument, Kml};
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+use crate::{
+    error::Error,
+    flightplan::{self, FlightPlan},
+    litchi,
+    mavlink::wpl,
+    store::{FLIGHTPLAN_FOLDER_NAME, SAVED_PLAN_FILE_NAME},
+};
+
+/// Where converted plans are written, told by the given path.
+pub enum Destination {
+    /// Laid out as FreeFlight expects: `flightPlan/<uuid>/savedPlan.json`
+    Directory(PathBuf),
+
+    /// A single plan's JSON, to a `.json` file
+    File(PathBuf),
+
+    /// Several plans, laid out like [`Destination::Directory`]
+    Zip(PathBuf),
+
+    /// One folder per plan, for review in Google Earth
+    Kml(PathBuf),
+
+    /// A single plan's `FeatureCollection`
+    GeoJson(PathBuf),
+
+    /// A single plan's route
+    Gpx(PathBuf),
+
+    /// A single plan, as a QGroundControl mission
+    QgcPlan(PathBuf),
+
+    /// A single plan, as a Mission Planner waypoint file
+    Waypoints(PathBuf),
+}
+
+impl TryFrom<&Path> for Destination {
+    type Error = Error;
+
+    /// Existing directories and paths without extension are directories,
+    /// `.zip` paths are bundles, `.kml`, `.geojson`, `.gpx`, `.plan` and `.waypoints` paths
+    /// are exports, `.json` paths are files. Other extensions are refused.
+    fn try_from(path: &Path) -> Result<Self, Self::Error> {
+        let extension = path.extension().and_then(|e| e.to_str());
+
+        let destination = match extension {
+            _ if path.is_dir() => Destination::Directory,
+            None => Destination::Directory,
+            Some(e) if e.eq_ignore_ascii_case("json") => Destination::File,
+            Some(e) if e.eq_ignore_ascii_case("zip") => Destination::Zip,
+            Some(e) if e.eq_ignore_ascii_case("kml") => Destination::Kml,
+            Some(e) if e.eq_ignore_ascii_case("geojson") => Destination::GeoJson,
+            Some(e) if e.eq_ignore_ascii_case("gpx") => Destination::Gpx,
+            Some(e) if e.eq_ignore_ascii_case("plan") => Destination::QgcPlan,
+            Some(e) if e.eq_ignore_ascii_case("waypoints") => Destination::Waypoints,
+            Some(_) => return Err(Error::UnsupportedConversion(path.to_path_buf())),
+        };
+
+        Ok(destination(path.to_path_buf()))
+    }
+}
+
+/// `flightPlan/<uuid>`, relative to the root of a FreeFlight layout.
+pub fn plan_folder(flightplan: &FlightPlan) -> PathBuf {
+    Path::new(FLIGHTPLAN_FOLDER_NAME).join(flightplan.folder_name())
+}
+
+impl Destination {
+    /// Writes the plans, then returns the files written:
+    /// one per plan in a directory, a single one otherwise.
+    /// `takeoff_elevation`, in meters above sea level, makes altitudes absolute where supported.
+    pub fn write(
+        &self,
+        flightplans: &[FlightPlan],
+        takeoff_elevation: Option<f64>,
+    ) -> Result<Vec<PathBuf>, Error> {
+        match self {
+            Destination::Directory(root) => flightplans
+                .iter()
+                .map(|flightplan| {
+                    let folder = root.join(plan_folder(flightplan));
+
+                    fs::create_dir_all(&folder)?;
+
+                    let path = folder.join(SAVED_PLAN_FILE_NAME);
+
+                    fs::write(&path, Vec::from(flightplan))?;
+
+                    Ok(path)
+                })
+                .collect(),
+
+            Destination::File(path) => match flightplans {
+                [flightplan] => {
+                    fs::write(path, Vec::from(flightplan))?;
+
+                    Ok(vec![path.clone()])
+                }
+
+                _ => Err(Error::SeveralPlansInOneFile(flightplans.len())),
+            },
+
+            Destination::Zip(path) => {
+                let mut zip = ZipWriter::new(fs::File::create(path)?);
+
+                let options =
+                    FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+                for flightplan in flightplans {
+                    // Zip entries always use forward slashes.
+                    let name = format!(
+                        "{}/{}/{}",
+                        FLIGHTPLAN_FOLDER_NAME,
+                        flightplan.folder_name(),
+                        SAVED_PLAN_FILE_NAME
+                    );
+
+                    zip.start_file(name, options)?;
+
+                    zip.write_all(&Vec::from(flightplan))?;
+                }
+
+                zip.finish()?;
+
+                Ok(vec![path.clone()])
+            }
+
+            Destination::Kml(path) => {
+                let kml = Kml::KmlDocument(KmlDocument {
+                    attrs: HashMap::from([(
+                        "xmlns".to_owned(),
+                        "http://www.opengis.net/kml/2.2".to_owned(),
+                    )]),
+                    elements: vec![flightplan::to_kml(flightplans, takeoff_elevation)],
+                    ..Default::default()
+                });
+
+                litchi::kml::to_writer(&kml, fs::File::create(path)?)?;
+
+                Ok(vec![path.clone()])
+            }
+
+            Destination::GeoJson(path) => match flightplans {
+                [flightplan] => {
+                    let geojson = flightplan.to_geojson();
+
+                    fs::write(path, format!("{:#}", geojson))?;
+
+                    Ok(vec![path.clone()])
+                }
+
+                _ => Err(Error::SeveralPlansInOneFile(flightplans.len())),
+            },
+
+            Destination::Gpx(path) => match flightplans {
+                [flightplan] => {
+                    flightplan.to_gpx(takeoff_elevation, fs::File::create(path)?)?;
+
+                    Ok(vec![path.clone()])
+                }
+
+                _ => Err(Error::SeveralPlansInOneFile(flightplans.len())),
+            },
+
+            Destination::QgcPlan(path) => match flightplans {
+                [flightplan] => {
+                    let plan = flightplan
+                        .to_qgc_plan(takeoff_elevation.ok_or(Error::NoTakeoffElevation)?)
+                        .to_json();
+
+                    fs::write(path, format!("{:#}", plan))?;
+
+                    Ok(vec![path.clone()])
+                }
+
+                _ => Err(Error::SeveralPlansInOneFile(flightplans.len())),
+            },
+
+            Destination::Waypoints(path) => match flightplans {
+                [flightplan] => {
+                    let items = flightplan
+                        .to_waypoints(takeoff_elevation.ok_or(Error::NoTakeoffElevation)?);
+
+                    wpl::to_writer(
+                        &items,
+                        wpl::MISSION_PLANNER_VERSION,
+                        fs::File::create(path)?,
+                    )?;
+
+                    Ok(vec![path.clone()])
+                }
+
+                _ => Err(Error::SeveralPlansInOneFile(flightplans.len())),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, io::Read, path::PathBuf};
+
+    use uuid::Uuid;
+    use zip::ZipArchive;
+
+    use crate::{
+        error::Error,
+        flightplan::{self, FlightPlan, PointOfInterest, Settings, Waypoint},
+        mavlink::wpl,
+    };
+
+    use super::Destination;
+
+    /// An empty directory of its own, for each test.
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("litchi2fp-{}", Uuid::new_v4()));
+
+        fs::create_dir_all(&dir).unwrap();
+
+        dir
+    }
+
+    fn flightplans() -> Vec<FlightPlan<'static>> {
+        ["pylon", "bridge"]
+            .into_iter()
+            .map(|title| {
+                let mut flightplan = FlightPlan::new(title, 48.8583, 2.2945);
+
+                flightplan.uuid = format!("uuid-{}", title);
+                flightplan.plan.waypoints = vec![Waypoint::at(48.8583, 2.2945)];
+
+                flightplan
+            })
+            .collect()
+    }
+
+    #[test]
+    fn directories_are_laid_out_as_on_the_device() {
+        let dir = temp_dir();
+
+        let paths = Destination::try_from(dir.as_path())
+            .unwrap()
+            .write(&flightplans(), None)
+            .unwrap();
+
+        assert_eq!(
+            paths,
+            [
+                dir.join("flightPlan/uuid-pylon/savedPlan.json"),
+                dir.join("flightPlan/uuid-bridge/savedPlan.json"),
+            ]
+        );
+
+        let saved: FlightPlan = serde_json::from_slice(&fs::read(&paths[1]).unwrap()).unwrap();
+
+        assert_eq!(saved.title, "bridge");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn zip_bundles_are_laid_out_as_on_the_device() {
+        let dir = temp_dir();
+        let path = dir.join("bundle.zip");
+
+        let paths = Destination::try_from(path.as_path())
+            .unwrap()
+            .write(&flightplans(), None)
+            .unwrap();
+
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[0], path);
+
+        let mut zip = ZipArchive::new(fs::File::open(&path).unwrap()).unwrap();
+
+        let names: Vec<_> = zip.file_names().collect();
+
+        assert_eq!(names.len(), 2);
+
+        for title in ["pylon", "bridge"] {
+            let name = format!("flightPlan/uuid-{}/savedPlan.json", title);
+
+            let mut json = String::new();
+
+            zip.by_name(&name)
+                .unwrap()
+                .read_to_string(&mut json)
+                .unwrap();
+
+            let saved: FlightPlan = serde_json::from_str(&json).unwrap();
+
+            assert_eq!(saved.title, title);
+        }
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn single_files_hold_a_single_plan() {
+        let dir = temp_dir();
+        let path = dir.join("plan.json");
+
+        let res = Destination::try_from(path.as_path())
+            .unwrap()
+            .write(&flightplans(), None);
+
+        assert!(matches!(res, Err(Error::SeveralPlansInOneFile(2))));
+        assert!(!path.exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn unknown_extensions_are_refused() {
+        for name in ["plan.csv", "plan.kmz"] {
+            let res = Destination::try_from(std::path::Path::new(name));
+
+            assert!(matches!(res, Err(Error::UnsupportedConversion(_))));
+        }
+
+        assert!(matches!(
+            Destination::try_from(std::path::Path::new("plan.JSON")),
+            Ok(Destination::File(_))
+        ));
+    }
+
+    #[test]
+    fn waypoint_files_read_back() {
+        let mut flightplan = FlightPlan::new("tower", 48.8583, 2.2945);
+
+        flightplan.plan.poi = vec![PointOfInterest {
+            latitude: 48.8590,
+            longitude: 2.2950,
+            altitude: 20,
+            color: 0x32a852,
+        }];
+
+        flightplan.plan.waypoints = vec![
+            Waypoint::at(48.8583, 2.2945).with_speed(8),
+            Waypoint::at(48.8588, 2.2945)
+                .with_altitude(45)
+                .with_poi(Some(0)),
+            Waypoint::at(48.8593, 2.2950).with_altitude(60),
+        ];
+
+        let dir = temp_dir();
+        let path = dir.join("tower.waypoints");
+
+        Destination::try_from(path.as_path())
+            .unwrap()
+            .write(&[flightplan], Some(35.0))
+            .unwrap();
+
+        let source = fs::read(&path).unwrap();
+        let items = wpl::from_reader(source.as_slice()).unwrap();
+
+        let (read_back, losses) =
+            flightplan::from_waypoints("tower", &items, &source, &Settings::default()).unwrap();
+
+        assert!(losses.is_empty(), "{:?}", losses);
+
+        let waypoints: Vec<_> = read_back
+            .plan
+            .waypoints
+            .iter()
+            .map(|w| (w.latitude, w.longitude, w.altitude, w.speed, w.poi))
+            .collect();
+
+        assert_eq!(
+            waypoints,
+            [
+                (48.8583, 2.2945, 30, 8, None),
+                (48.8588, 2.2945, 45, 5, Some(0)),
+                (48.8593, 2.2950, 60, 5, None),
+            ]
+        );
+
+        assert_eq!(read_back.plan.poi.len(), 1);
+        assert_eq!(read_back.plan.poi[0].altitude, 20);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,95 @@
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use crate::{error::Error, flightplan::FlightPlan};
+
+use super::{
+    FlightPlanStore, FLIGHTPLAN_FOLDER_NAME, FREEFLIGHT6_FOLDER_NAME, SAVED_PLAN_FILE_NAME,
+};
+
+/// A FreeFlight tree in a local directory,
+/// e.g. a device mounted through gvfs or jmtpfs, or a copy of its storage.
+pub struct DirectoryStore {
+    /// The `FreeFlight 6/flightPlan` folder, created on first write
+    flightplans: PathBuf,
+}
+
+impl DirectoryStore {
+    /// Opens the tree under `root`, which must hold a `FreeFlight 6` folder.
+    pub fn open(root: &Path) -> Result<Self, Error> {
+        let ff6 = root.join(FREEFLIGHT6_FOLDER_NAME);
+
+        if !ff6.is_dir() {
+            return Err(Error::NoFreeFlight6Directory(root.to_path_buf()));
+        }
+
+        Ok(DirectoryStore {
+            flightplans: ff6.join(FLIGHTPLAN_FOLDER_NAME),
+        })
+    }
+}
+
+impl FlightPlanStore for DirectoryStore {
+    fn list(&mut self) -> Result<Vec<String>, Error> {
+        let entries = match fs::read_dir(&self.flightplans) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut folders = vec![];
+
+        for entry in entries {
+            let entry = entry?;
+
+            if entry.file_type()?.is_dir() {
+                if let Some(name) = entry.file_name().to_str() {
+                    folders.push(name.to_owned());
+                }
+            }
+        }
+
+        folders.sort();
+
+        Ok(folders)
+    }
+
+    fn read(&mut self, folder: &str) -> Result<Option<Vec<u8>>, Error> {
+        match fs::read(self.flightplans.join(folder).join(SAVED_PLAN_FILE_NAME)) {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn exists(&mut self, folder: &str) -> Result<bool, Error> {
+        Ok(self
+            .flightplans
+            .join(folder)
+            .join(SAVED_PLAN_FILE_NAME)
+            .is_file())
+    }
+
+    fn write(&mut self, folder: &str, flightplan: &FlightPlan) -> Result<(), Error> {
+        let folder = self.flightplans.join(folder);
+
+        fs::create_dir_all(&folder)?;
+
+        fs::write(folder.join(SAVED_PLAN_FILE_NAME), Vec::from(flightplan))?;
+
+        Ok(())
+    }
+
+    fn delete(&mut self, folder: &str) -> Result<(), Error> {
+        match fs::remove_dir_all(self.flightplans.join(folder)) {
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                Err(Error::FlightPlanNotFound(folder.to_owned()))
+            }
+
+            result => Ok(result?),
+        }
+    }
+}
@@ -0,0 +1,255 @@
+use std::{fmt::Display, path::PathBuf};
+
+use clap::{Args, ValueEnum};
+
+use crate::{error::Error, flightplan::FlightPlan, mtp};
+
+pub use directory::DirectoryStore;
+
+mod directory;
+
+pub const FREEFLIGHT6_FOLDER_NAME: &str = "FreeFlight 6";
+pub const FLIGHTPLAN_FOLDER_NAME: &str = "flightPlan";
+pub const SAVED_PLAN_FILE_NAME: &str = "savedPlan.json";
+
+/// FreeFlight's `FreeFlight 6/flightPlan` tree, where each plan is a `<uuid>/savedPlan.json`.
+pub trait FlightPlanStore {
+    /// Names of the folders holding a plan.
+    fn list(&mut self) -> Result<Vec<String>, Error>;
+
+    /// Content of the `savedPlan.json` in `folder`, `None` if there is none.
+    fn read(&mut self, folder: &str) -> Result<Option<Vec<u8>>, Error>;
+
+    /// Whether there is a `savedPlan.json` in `folder`, without reading it.
+    fn exists(&mut self, folder: &str) -> Result<bool, Error>;
+
+    /// Writes `flightplan` as the `savedPlan.json` in `folder`, creating the folder or replacing the file if needed.
+    fn write(&mut self, folder: &str, flightplan: &FlightPlan) -> Result<(), Error>;
+
+    /// Deletes `folder` and everything in it.
+    fn delete(&mut self, folder: &str) -> Result<(), Error>;
+}
+
+/// Where FreeFlight's tree is found.
+#[derive(Debug, Args)]
+pub struct Location {
+    /// Use the FreeFlight data in this directory instead of an MTP device,
+    /// e.g. a device mounted with gvfs or jmtpfs. It must contain the `FreeFlight 6` folder
+    #[clap(long)]
+    pub mount: Option<PathBuf>,
+
+    /// Device to use, as hex USB ids `<vendor>:<product>` or a serial number, see `device ls`
+    #[clap(long)]
+    pub device: Option<String>,
+
+    /// Storage of the device to use, as its id or description,
+    /// when FreeFlight's folder is on several of them
+    #[clap(long)]
+    pub storage: Option<String>,
+
+    /// How many times to upload a plan again when it doesn't read back from the device as sent
+    #[clap(long, default_value_t = mtp::DEFAULT_UPLOAD_RETRIES)]
+    pub upload_retries: u32,
+}
+
+impl Location {
+    /// Opens the tree found under `mount` if given, on an MTP device otherwise.
+    pub fn open(&self) -> Result<Box<dyn FlightPlanStore>, Error> {
+        match &self.mount {
+            Some(root) => Ok(Box::new(DirectoryStore::open(root)?)),
+
+            None => {
+                let device = mtp::find_device(self.device.as_deref())?;
+
+                let store = mtp::MtpStore::open(device, self.storage.as_deref())?;
+
+                Ok(Box::new(store.with_upload_retries(self.upload_retries)))
+            }
+        }
+    }
+}
+
+/// A flight plan found in a store.
+pub struct StoredFlightPlan {
+    /// Name of the folder holding the plan, normally its UUID
+    pub folder: String,
+
+    /// Content of `savedPlan.json`, as stored
+    pub data: Vec<u8>,
+
+    pub flightplan: FlightPlan<'static>,
+}
+
+impl StoredFlightPlan {
+    /// Whether `selector` is this plan's UUID or title.
+    pub fn matches(&self, selector: &str) -> bool {
+        self.flightplan.uuid == selector || self.flightplan.title == selector
+    }
+}
+
+/// What to do when storing a plan whose UUID is already stored.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ConflictPolicy {
+    /// Replace the stored plan
+    #[default]
+    Overwrite,
+
+    /// Keep the stored plan
+    Skip,
+
+    /// Store the new plan under a new UUID
+    Rename,
+
+    /// Stop with an error
+    Fail,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum StoreOutcome {
+    Created,
+    Overwritten,
+    Skipped,
+
+    /// Stored under this new UUID
+    Renamed(String),
+}
+
+impl Display for StoreOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Created => write!(f, "created"),
+            Self::Overwritten => write!(f, "overwritten"),
+            Self::Skipped => write!(f, "skipped, already on the device"),
+            Self::Renamed(uuid) => write!(f, "created as {}", uuid),
+        }
+    }
+}
+
+/// Lists the stored flight plans.
+/// Folders without a readable `savedPlan.json` are skipped.
+pub fn list_flightplans(store: &mut dyn FlightPlanStore) -> Result<Vec<StoredFlightPlan>, Error> {
+    let mut flightplans = vec![];
+
+    for folder in store.list()? {
+        let data = match store.read(&folder)? {
+            Some(data) => data,
+            None => continue,
+        };
+
+        match serde_json::from_slice(&data) {
+            Ok(flightplan) => flightplans.push(StoredFlightPlan {
+                folder,
+                data,
+                flightplan,
+            }),
+
+            Err(e) => eprintln!(
+                "Skipping '{}': unreadable {}: {}",
+                folder, SAVED_PLAN_FILE_NAME, e
+            ),
+        }
+    }
+
+    Ok(flightplans)
+}
+
+pub fn store_flightplan(
+    store: &mut dyn FlightPlanStore,
+    flightplan: &FlightPlan,
+    on_conflict: ConflictPolicy,
+) -> Result<StoreOutcome, Error> {
+    let folder = flightplan.folder_name();
+
+    if !store.exists(&folder)? {
+        store.write(&folder, flightplan)?;
+
+        return Ok(StoreOutcome::Created);
+    }
+
+    match on_conflict {
+        ConflictPolicy::Overwrite => {
+            store.write(&folder, flightplan)?;
+
+            Ok(StoreOutcome::Overwritten)
+        }
+
+        ConflictPolicy::Skip => Ok(StoreOutcome::Skipped),
+
+        ConflictPolicy::Rename => {
+            let mut renamed = flightplan.clone();
+
+            renamed.renew_uuid();
+
+            store.write(&renamed.folder_name(), &renamed)?;
+
+            Ok(StoreOutcome::Renamed(renamed.uuid))
+        }
+
+        ConflictPolicy::Fail => Err(Error::FlightPlanConflict(flightplan.uuid.clone())),
+    }
+}
+
+/// Deletes a plan's `savedPlan.json`, anything else in its folder, then the folder itself.
+pub fn delete_flightplan(
+    store: &mut dyn FlightPlanStore,
+    stored: &StoredFlightPlan,
+) -> Result<(), Error> {
+    store.delete(&stored.folder)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::PathBuf};
+
+    use super::{
+        list_flightplans, store_flightplan, ConflictPolicy, DirectoryStore, StoreOutcome,
+        FLIGHTPLAN_FOLDER_NAME, FREEFLIGHT6_FOLDER_NAME,
+    };
+    use crate::flightplan::FlightPlan;
+
+    fn temporary_tree() -> PathBuf {
+        let root = env::temp_dir().join(format!("litchi2fp-{}", uuid::Uuid::new_v4()));
+
+        fs::create_dir_all(root.join(FREEFLIGHT6_FOLDER_NAME)).unwrap();
+
+        root
+    }
+
+    #[test]
+    fn conflicts_follow_policy() {
+        let root = temporary_tree();
+
+        let mut store = DirectoryStore::open(&root).unwrap();
+
+        let flightplan = FlightPlan::new("test", 48.8583, 2.2945);
+
+        let mut store_with = |policy| store_flightplan(&mut store, &flightplan, policy).unwrap();
+
+        assert_eq!(store_with(ConflictPolicy::Fail), StoreOutcome::Created);
+        assert_eq!(store_with(ConflictPolicy::Skip), StoreOutcome::Skipped);
+        assert_eq!(
+            store_with(ConflictPolicy::Overwrite),
+            StoreOutcome::Overwritten
+        );
+        assert!(matches!(
+            store_with(ConflictPolicy::Rename),
+            StoreOutcome::Renamed(_)
+        ));
+
+        assert!(store_flightplan(&mut store, &flightplan, ConflictPolicy::Fail).is_err());
+
+        let stored = list_flightplans(&mut store).unwrap();
+
+        assert_eq!(stored.len(), 2);
+        assert!(stored.iter().all(|s| s.matches("test")));
+
+        assert!(root
+            .join(FREEFLIGHT6_FOLDER_NAME)
+            .join(FLIGHTPLAN_FOLDER_NAME)
+            .join(&flightplan.uuid)
+            .join(super::SAVED_PLAN_FILE_NAME)
+            .is_file());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
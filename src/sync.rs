@@ -0,0 +1,192 @@
+use std::{fmt::Display, fs, path::Path, path::PathBuf};
+
+use crate::{
+    error::Error,
+    flightplan::FlightPlan,
+    store::{self, FlightPlanStore, StoredFlightPlan},
+};
+
+/// Extensions of the mission and plan files picked up in a folder.
+const MISSION_EXTENSIONS: [&str; 6] = ["csv", "kml", "bin", "geojson", "gpx", "waypoints"];
+
+/// Mission files directly in `dir`, sorted by name.
+pub fn missions(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut paths = vec![];
+
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_ascii_lowercase);
+
+        if path.is_file() && extension.is_some_and(|e| MISSION_EXTENSIONS.contains(&e.as_str())) {
+            paths.push(path);
+        }
+    }
+
+    paths.sort();
+
+    Ok(paths)
+}
+
+/// What to do with one plan to bring the store in line with the local missions.
+pub enum Change<'a, 'f> {
+    Create(&'a FlightPlan<'f>),
+    Update(&'a FlightPlan<'f>),
+    Unchanged(&'a FlightPlan<'f>),
+    Delete(&'a StoredFlightPlan),
+}
+
+impl Display for Change<'_, '_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (verb, flightplan) = match self {
+            Change::Create(flightplan) => ("create", *flightplan),
+            Change::Update(flightplan) => ("update", *flightplan),
+            Change::Unchanged(flightplan) => ("unchanged", *flightplan),
+            Change::Delete(stored) => ("delete", &stored.flightplan),
+        };
+
+        write!(
+            f,
+            "{:<9}  '{}' ({})",
+            verb, flightplan.title, flightplan.uuid
+        )
+    }
+}
+
+/// Compares local plans with stored ones by UUID, then by content.
+/// With `prune`, stored plans converted by litchi2fp but missing locally are deleted,
+/// plans made on the tablet are always kept, as are plans with a random UUID,
+/// see [`FlightPlan::is_converted`].
+pub fn plan<'a, 'f>(
+    local: &'a [FlightPlan<'f>],
+    stored: &'a [StoredFlightPlan],
+    prune: bool,
+) -> Vec<Change<'a, 'f>> {
+    let mut changes: Vec<_> = local
+        .iter()
+        .map(
+            |flightplan| match stored.iter().find(|s| s.flightplan.uuid == flightplan.uuid) {
+                None => Change::Create(flightplan),
+                Some(s) if flightplan.same_content(&s.flightplan) => Change::Unchanged(flightplan),
+                Some(_) => Change::Update(flightplan),
+            },
+        )
+        .collect();
+
+    if prune {
+        let missing = stored.iter().filter(|s| {
+            s.flightplan.is_converted() && local.iter().all(|l| l.uuid != s.flightplan.uuid)
+        });
+
+        changes.extend(missing.map(Change::Delete));
+    }
+
+    changes
+}
+
+pub fn run(
+    store: &mut dyn FlightPlanStore,
+    local: &[FlightPlan],
+    prune: bool,
+    dry_run: bool,
+) -> Result<(), Error> {
+    let stored = store::list_flightplans(store)?;
+
+    let changes = plan(local, &stored, prune);
+
+    let (mut created, mut updated, mut unchanged, mut deleted) = (0, 0, 0, 0);
+
+    for change in &changes {
+        println!("{}", change);
+
+        match change {
+            Change::Create(_) => created += 1,
+            Change::Update(_) => updated += 1,
+            Change::Unchanged(_) => unchanged += 1,
+            Change::Delete(_) => deleted += 1,
+        }
+    }
+
+    println!(
+        "{} to create, {} to update, {} unchanged, {} to delete",
+        created, updated, unchanged, deleted
+    );
+
+    if dry_run {
+        println!("Dry run, nothing changed.");
+
+        return Ok(());
+    }
+
+    for change in changes {
+        match change {
+            Change::Create(flightplan) | Change::Update(flightplan) => {
+                store.write(&flightplan.folder_name(), flightplan)?
+            }
+
+            Change::Delete(stored) => store::delete_flightplan(store, stored)?,
+
+            Change::Unchanged(_) => (),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{plan, Change};
+    use crate::{flightplan::FlightPlan, store::StoredFlightPlan};
+
+    fn stored(flightplan: &FlightPlan) -> StoredFlightPlan {
+        let data = Vec::from(flightplan);
+
+        StoredFlightPlan {
+            folder: flightplan.folder_name(),
+            flightplan: serde_json::from_slice(&data).unwrap(),
+            data,
+        }
+    }
+
+    fn converted(title: &str) -> FlightPlan<'static> {
+        let mut flightplan = FlightPlan::new(title, 48.8583, 2.2945);
+
+        flightplan.uuid =
+            uuid::Uuid::new_v5(&uuid::Uuid::NAMESPACE_OID, title.as_bytes()).to_string();
+
+        flightplan
+    }
+
+    #[test]
+    fn changes_are_planned_by_uuid_and_content() {
+        let same = converted("same");
+        let changed = converted("changed");
+        let new = converted("new");
+        let gone = converted("gone");
+        let tablet = FlightPlan::new("tablet", 48.8583, 2.2945);
+
+        let mut edited = changed.clone();
+        edited.zoom_level += 1.0;
+
+        let mut later = same.clone();
+        later.date += 1000;
+
+        let local = [later, edited, new];
+        let on_device = [same, changed, gone, tablet].map(|f| stored(&f));
+
+        let changes: Vec<_> = plan(&local, &on_device, true)
+            .iter()
+            .map(|change| match change {
+                Change::Create(f) => format!("+{}", f.title),
+                Change::Update(f) => format!("~{}", f.title),
+                Change::Unchanged(f) => format!("={}", f.title),
+                Change::Delete(s) => format!("-{}", s.flightplan.title),
+            })
+            .collect();
+
+        assert_eq!(changes, ["=same", "~changed", "+new", "-gone"]);
+    }
+}
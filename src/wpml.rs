@@ -0,0 +1,811 @@
+//! DJI WPML missions: a `.kmz` archive holding `wpmz/template.kml`, the mission as
+//! edited in DJI's apps, and `wpmz/waylines.wpml`, the mission flown by the aircraft.
+
+use std::io::{Seek, Write};
+
+use clap::ValueEnum;
+use quick_xml::{
+    events::{BytesDecl, BytesEnd, BytesStart, BytesText, Event},
+    Writer,
+};
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+use crate::{
+    error::Error,
+    flightplan::{bearing, distance},
+    litchi::{
+        bin::{FinishAction, LitchiMission, PathMode, PhotoInterval},
+        csv::de::{Altitude, GimbalSettings, MissionRecord},
+        Action,
+    },
+};
+
+const KML_NAMESPACE: &str = "http://www.opengis.net/kml/2.2";
+const WPML_NAMESPACE: &str = "http://www.dji.com/wpmz/1.0.2";
+
+/// Speed of CSV missions, whose records at 0 m/s fly at the mission's speed.
+const DEFAULT_SPEED_MS: f64 = 5.0;
+
+/// Camera of the aircraft, for models with a single one.
+const PAYLOAD_POSITION: &str = "0";
+
+/// Height the aircraft climbs to after takeoff before flying to the first waypoint
+const TAKEOFF_SECURITY_HEIGHT_M: f64 = 20.0;
+
+/// Aircraft flying WPML missions, with the camera they come with.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Aircraft {
+    M30,
+    M30t,
+    #[default]
+    M3e,
+    M3t,
+    M3m,
+    M3d,
+    M3td,
+}
+
+impl Aircraft {
+    /// Values of `wpml:droneEnumValue`, `wpml:droneSubEnumValue` and `wpml:payloadEnumValue`.
+    fn enum_values(self) -> (u16, u16, u16) {
+        use Aircraft::*;
+
+        match self {
+            M30 => (67, 0, 52),
+            M30t => (67, 1, 53),
+            M3e => (77, 0, 66),
+            M3t => (77, 1, 67),
+            M3m => (77, 2, 68),
+            M3d => (91, 0, 80),
+            M3td => (91, 1, 81),
+        }
+    }
+}
+
+pub struct Mission<'m> {
+    /// Value of `wpml:finishAction`
+    finish_action: &'static str,
+
+    speed: f64,
+    waypoints: Vec<Waypoint<'m>>,
+}
+
+struct Waypoint<'m> {
+    latitude: f64,
+    longitude: f64,
+
+    /// Relative to the takeoff point
+    height: f64,
+
+    /// Whether the height, or the point of interest's, was meant above ground
+    above_ground: bool,
+
+    /// Degrees clockwise from north
+    heading: f64,
+
+    speed: Option<f64>,
+
+    /// Latitude, longitude and altitude of the point of interest looked at
+    poi: Option<[f64; 3]>,
+
+    /// Distance from the waypoint at which the turn starts, 0 to stop at the waypoint
+    curve: f64,
+
+    gimbal: Option<&'m GimbalSettings>,
+    interval: Option<PhotoInterval>,
+    actions: &'m [Action],
+}
+
+impl<'m> From<&'m LitchiMission> for Mission<'m> {
+    fn from(mission: &'m LitchiMission) -> Self {
+        let waypoints = mission
+            .waypoints
+            .iter()
+            .map(|w| Waypoint {
+                latitude: w.latitude,
+                longitude: w.longitude,
+                height: w.altitude as f64,
+                above_ground: w.above_ground
+                    || w.poi
+                        .and_then(|index| mission.poi.get(index as usize))
+                        .is_some_and(|poi| poi.above_ground),
+                heading: w.heading as f64,
+                speed: None,
+                poi: w
+                    .poi
+                    .and_then(|index| mission.poi.get(index as usize))
+                    .map(|poi| [poi.latitude, poi.longitude, poi.altitude as f64]),
+                curve: match mission.path_mode {
+                    PathMode::StraigtLines => 0.0,
+                    PathMode::CurvedTurns => w.curve_size as f64,
+                },
+                gimbal: w.gimbal.as_ref(),
+                interval: w.interval,
+                actions: &w.actions,
+            })
+            .collect();
+
+        // WPML has no way back along the path.
+        let finish_action = match mission.finish_action {
+            FinishAction::None | FinishAction::Reverse => "noAction",
+            FinishAction::ReturnToHome => "goHome",
+            FinishAction::Land => "autoLand",
+            FinishAction::BackToFirst => "gotoFirstWaypoint",
+        };
+
+        Mission {
+            finish_action,
+            speed: mission.cruising_speed as f64,
+            waypoints,
+        }
+    }
+}
+
+/// CSV missions have no finish action, the aircraft hovers at the last waypoint.
+impl<'m> From<&'m [MissionRecord]> for Mission<'m> {
+    fn from(records: &'m [MissionRecord]) -> Self {
+        let waypoints = records
+            .iter()
+            .map(|record| Waypoint {
+                latitude: record.waypoint.latitude,
+                longitude: record.waypoint.longitude,
+                height: height(&record.waypoint.altitude),
+                above_ground: [Some(&record.waypoint), record.poi.as_ref()]
+                    .into_iter()
+                    .flatten()
+                    .any(|c| matches!(c.altitude, Altitude::AboveGround(_))),
+                heading: record.heading,
+                speed: (record.speed > 0.0).then_some(record.speed as f64),
+                poi: record
+                    .poi
+                    .as_ref()
+                    .map(|poi| [poi.latitude, poi.longitude, height(&poi.altitude)]),
+                curve: record.curvesize,
+                gimbal: record.gimbal.as_ref(),
+                interval: if record.photo_timeinterval > 0 {
+                    Some(PhotoInterval::Time {
+                        seconds: record.photo_timeinterval as f32,
+                    })
+                } else if record.photo_distinterval > 0 {
+                    Some(PhotoInterval::Distance {
+                        meters: record.photo_distinterval as f32,
+                    })
+                } else {
+                    None
+                },
+                actions: &record.actions,
+            })
+            .collect();
+
+        Mission {
+            finish_action: "noAction",
+            speed: DEFAULT_SPEED_MS,
+            waypoints,
+        }
+    }
+}
+
+fn height(altitude: &Altitude) -> f64 {
+    match altitude {
+        Altitude::AboveGround(height) | Altitude::Absolute(height) => *height as f64,
+    }
+}
+
+/// Headings of WPML go from -180° to 180°.
+fn heading(degrees: f64) -> f64 {
+    let degrees = degrees.rem_euclid(360.0);
+
+    if degrees > 180.0 {
+        degrees - 360.0
+    } else {
+        degrees
+    }
+}
+
+impl<'m> Mission<'m> {
+    /// Altitudes above ground, which are flown above the takeoff point instead.
+    pub fn losses(&self) -> Vec<String> {
+        self.waypoints
+            .iter()
+            .enumerate()
+            .filter(|(_, waypoint)| waypoint.above_ground)
+            .map(|(index, _)| {
+                format!(
+                    "waypoint {} is above ground, flown above the takeoff point instead",
+                    index + 1
+                )
+            })
+            .collect()
+    }
+
+    /// Writes the `.kmz` archive for `aircraft`, ready to be imported in DJI's apps.
+    pub fn to_kmz<W: Write + Seek>(&self, aircraft: Aircraft, writer: W) -> Result<(), Error> {
+        let mut zip = ZipWriter::new(writer);
+
+        let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+        zip.start_file("wpmz/template.kml", options)?;
+        self.write(true, aircraft, &mut zip)?;
+
+        zip.start_file("wpmz/waylines.wpml", options)?;
+        self.write(false, aircraft, &mut zip)?;
+
+        zip.finish()?;
+
+        Ok(())
+    }
+
+    /// Seconds to fly along the path, without the time spent at waypoints.
+    fn duration(&self) -> f64 {
+        self.waypoints
+            .windows(2)
+            .map(|pair| {
+                let length = distance(
+                    pair[0].latitude,
+                    pair[0].longitude,
+                    pair[1].latitude,
+                    pair[1].longitude,
+                );
+
+                length / pair[0].speed.unwrap_or(self.speed)
+            })
+            .sum()
+    }
+
+    fn distance(&self) -> f64 {
+        self.waypoints
+            .windows(2)
+            .map(|pair| {
+                distance(
+                    pair[0].latitude,
+                    pair[0].longitude,
+                    pair[1].latitude,
+                    pair[1].longitude,
+                )
+            })
+            .sum()
+    }
+
+    /// The template when `template` is set, else the waylines.
+    fn write<W: Write>(&self, template: bool, aircraft: Aircraft, writer: W) -> Result<(), Error> {
+        let mut writer = WpmlWriter(Writer::new_with_indent(writer, b' ', 2));
+
+        writer.event(Event::Decl(BytesDecl::new(b"1.0", Some(b"UTF-8"), None)))?;
+
+        writer.start(
+            "kml",
+            &[("xmlns", KML_NAMESPACE), ("xmlns:wpml", WPML_NAMESPACE)],
+        )?;
+        writer.start("Document", &[])?;
+
+        if template {
+            writer.text("wpml:author", "litchi2fp")?;
+        }
+
+        writer.start("wpml:missionConfig", &[])?;
+        writer.text("wpml:flyToWaylineMode", "safely")?;
+        writer.text("wpml:finishAction", self.finish_action)?;
+        writer.text("wpml:exitOnRCLost", "executeLostAction")?;
+        writer.text("wpml:executeRCLostAction", "goBack")?;
+        writer.text(
+            "wpml:takeOffSecurityHeight",
+            &TAKEOFF_SECURITY_HEIGHT_M.to_string(),
+        )?;
+        writer.text("wpml:globalTransitionalSpeed", &self.speed.to_string())?;
+
+        let (drone, drone_sub, payload) = aircraft.enum_values();
+
+        writer.start("wpml:droneInfo", &[])?;
+        writer.text("wpml:droneEnumValue", &drone.to_string())?;
+        writer.text("wpml:droneSubEnumValue", &drone_sub.to_string())?;
+        writer.end("wpml:droneInfo")?;
+
+        writer.start("wpml:payloadInfo", &[])?;
+        writer.text("wpml:payloadEnumValue", &payload.to_string())?;
+        writer.text("wpml:payloadPositionIndex", PAYLOAD_POSITION)?;
+        writer.end("wpml:payloadInfo")?;
+
+        writer.end("wpml:missionConfig")?;
+
+        writer.start("Folder", &[])?;
+
+        if template {
+            writer.text("wpml:templateType", "waypoint")?;
+            writer.text("wpml:templateId", "0")?;
+
+            writer.start("wpml:waylineCoordinateSysParam", &[])?;
+            writer.text("wpml:coordinateMode", "WGS84")?;
+            writer.text("wpml:heightMode", "relativeToStartPoint")?;
+            writer.end("wpml:waylineCoordinateSysParam")?;
+
+            writer.text("wpml:autoFlightSpeed", &self.speed.to_string())?;
+            writer.text("wpml:gimbalPitchMode", "usePointSetting")?;
+            writer.text("wpml:globalUseStraightLine", "1")?;
+        } else {
+            writer.text("wpml:templateId", "0")?;
+            writer.text("wpml:executeHeightMode", "relativeToStartPoint")?;
+            writer.text("wpml:waylineId", "0")?;
+            writer.text("wpml:distance", &self.distance().round().to_string())?;
+            writer.text("wpml:duration", &self.duration().round().to_string())?;
+            writer.text("wpml:autoFlightSpeed", &self.speed.to_string())?;
+        }
+
+        let mut group_id = 0;
+
+        for (index, waypoint) in self.waypoints.iter().enumerate() {
+            writer.start("Placemark", &[])?;
+
+            writer.start("Point", &[])?;
+            writer.text(
+                "coordinates",
+                &format!("{},{}", waypoint.longitude, waypoint.latitude),
+            )?;
+            writer.end("Point")?;
+
+            writer.text("wpml:index", &index.to_string())?;
+
+            let height = waypoint.height.to_string();
+            let speed = waypoint.speed.unwrap_or(self.speed).to_string();
+
+            if template {
+                writer.text("wpml:useGlobalHeight", "0")?;
+                writer.text("wpml:ellipsoidHeight", &height)?;
+                writer.text("wpml:height", &height)?;
+                writer.text("wpml:useGlobalSpeed", "0")?;
+                writer.text("wpml:waypointSpeed", &speed)?;
+                writer.text("wpml:useGlobalHeadingParam", "0")?;
+            } else {
+                writer.text("wpml:executeHeight", &height)?;
+                writer.text("wpml:waypointSpeed", &speed)?;
+            }
+
+            writer.start("wpml:waypointHeadingParam", &[])?;
+
+            match waypoint.poi {
+                Some([latitude, longitude, altitude]) => {
+                    writer.text("wpml:waypointHeadingMode", "towardPOI")?;
+                    writer.text(
+                        "wpml:waypointPoiPoint",
+                        &format!("{},{},{}", latitude, longitude, altitude),
+                    )?;
+                }
+
+                None => {
+                    writer.text("wpml:waypointHeadingMode", "smoothTransition")?;
+                    writer.text(
+                        "wpml:waypointHeadingAngle",
+                        &heading(waypoint.heading).to_string(),
+                    )?;
+                    writer.text("wpml:waypointHeadingAngleEnable", "1")?;
+                }
+            }
+
+            writer.text("wpml:waypointHeadingPathMode", "followBadArc")?;
+            writer.end("wpml:waypointHeadingParam")?;
+
+            if template {
+                writer.text("wpml:useGlobalTurnParam", "0")?;
+            }
+
+            writer.start("wpml:waypointTurnParam", &[])?;
+
+            if waypoint.curve > 0.0 {
+                writer.text("wpml:waypointTurnMode", "coordinateTurn")?;
+                writer.text("wpml:waypointTurnDampingDist", &waypoint.curve.to_string())?;
+            } else {
+                writer.text(
+                    "wpml:waypointTurnMode",
+                    "toPointAndStopWithDiscontinuityCurvature",
+                )?;
+            }
+
+            writer.end("wpml:waypointTurnParam")?;
+            writer.text("wpml:useStraightLine", "1")?;
+
+            let pitch = match waypoint.gimbal {
+                Some(GimbalSettings::Interpolate(pitch)) => Some(*pitch),
+                _ => None,
+            };
+
+            if let (true, Some(pitch)) = (template, pitch) {
+                writer.text("wpml:gimbalPitchAngle", &pitch.to_string())?;
+            }
+
+            let mut actions = vec![];
+
+            // Later pitches are reached evenly from the previous waypoint.
+            if let (0, Some(pitch)) = (index, pitch) {
+                actions.push(WpmlAction::TiltCamera(pitch));
+            }
+
+            // Rotations turn the shorter way, from where the aircraft looks at.
+            let mut yaw = match waypoint.poi {
+                Some([latitude, longitude, _]) => {
+                    bearing(waypoint.latitude, waypoint.longitude, latitude, longitude)
+                }
+                None => waypoint.heading,
+            };
+
+            actions.extend(waypoint.actions.iter().map(|action| match action {
+                Action::RotateAircraft { angle } => {
+                    let heading = *angle as f64;
+                    let clockwise = (heading - yaw).rem_euclid(360.0) <= 180.0;
+
+                    yaw = heading;
+
+                    WpmlAction::RotateYaw { heading, clockwise }
+                }
+
+                action => WpmlAction::Litchi(action),
+            }));
+
+            let next = (index + 1).min(self.waypoints.len() - 1);
+
+            writer.action_group(&mut group_id, index, index, "reachPoint", None, &actions)?;
+
+            if let Some(GimbalSettings::Interpolate(pitch)) = self.waypoints[next].gimbal {
+                if next != index {
+                    writer.action_group(
+                        &mut group_id,
+                        index,
+                        next,
+                        "betweenAdjacentPoints",
+                        None,
+                        &[WpmlAction::TiltCameraEvenly(*pitch)],
+                    )?;
+                }
+            }
+
+            let interval = match waypoint.interval {
+                Some(PhotoInterval::Time { seconds }) => Some(("multipleTiming", seconds)),
+                Some(PhotoInterval::Distance { meters }) => Some(("multipleDistance", meters)),
+                None => None,
+            };
+
+            if let Some((trigger, parameter)) = interval {
+                writer.action_group(
+                    &mut group_id,
+                    index,
+                    next,
+                    trigger,
+                    Some(parameter),
+                    &[WpmlAction::Litchi(&Action::TakePhoto)],
+                )?;
+            }
+
+            writer.end("Placemark")?;
+        }
+
+        writer.end("Folder")?;
+        writer.end("Document")?;
+        writer.end("kml")?;
+
+        Ok(())
+    }
+}
+
+enum WpmlAction<'a> {
+    /// Any action but rotations, which need their direction
+    Litchi(&'a Action),
+
+    /// Degrees clockwise from north
+    RotateYaw {
+        heading: f64,
+        clockwise: bool,
+    },
+
+    /// Absolute pitch, in degrees
+    TiltCamera(f64),
+    TiltCameraEvenly(f64),
+}
+
+struct WpmlWriter<W: Write>(Writer<W>);
+
+impl<W: Write> WpmlWriter<W> {
+    fn event(&mut self, event: Event) -> Result<(), Error> {
+        Ok(self.0.write_event(event)?)
+    }
+
+    fn start(&mut self, name: &str, attributes: &[(&str, &str)]) -> Result<(), Error> {
+        let start = BytesStart::borrowed_name(name.as_bytes());
+
+        self.event(Event::Start(
+            start.with_attributes(attributes.iter().copied()),
+        ))
+    }
+
+    fn end(&mut self, name: &str) -> Result<(), Error> {
+        self.event(Event::End(BytesEnd::borrowed(name.as_bytes())))
+    }
+
+    /// An element holding only text.
+    fn text(&mut self, name: &str, text: &str) -> Result<(), Error> {
+        self.start(name, &[])?;
+        self.event(Event::Text(BytesText::from_plain_str(text)))?;
+        self.end(name)
+    }
+
+    /// Actions run in sequence from waypoint `start` to waypoint `end`, none written when empty.
+    fn action_group(
+        &mut self,
+        id: &mut usize,
+        start: usize,
+        end: usize,
+        trigger: &str,
+        parameter: Option<f32>,
+        actions: &[WpmlAction],
+    ) -> Result<(), Error> {
+        if actions.is_empty() {
+            return Ok(());
+        }
+
+        self.start("wpml:actionGroup", &[])?;
+        self.text("wpml:actionGroupId", &id.to_string())?;
+        self.text("wpml:actionGroupStartIndex", &start.to_string())?;
+        self.text("wpml:actionGroupEndIndex", &end.to_string())?;
+        self.text("wpml:actionGroupMode", "sequence")?;
+
+        self.start("wpml:actionTrigger", &[])?;
+        self.text("wpml:actionTriggerType", trigger)?;
+
+        if let Some(parameter) = parameter {
+            self.text("wpml:actionTriggerParam", &parameter.to_string())?;
+        }
+
+        self.end("wpml:actionTrigger")?;
+
+        for (action_id, action) in actions.iter().enumerate() {
+            self.start("wpml:action", &[])?;
+            self.text("wpml:actionId", &action_id.to_string())?;
+            self.action(action)?;
+            self.end("wpml:action")?;
+        }
+
+        self.end("wpml:actionGroup")?;
+
+        *id += 1;
+
+        Ok(())
+    }
+
+    /// The function of an action and its parameters.
+    fn action(&mut self, action: &WpmlAction) -> Result<(), Error> {
+        let (function, parameters): (_, Vec<(_, String)>) = match action {
+            WpmlAction::Litchi(Action::StayFor { ms }) => (
+                "hover",
+                vec![("hoverTime", (*ms as f64 / 1000.0).to_string())],
+            ),
+
+            WpmlAction::Litchi(Action::TakePhoto) => ("takePhoto", vec![]),
+            WpmlAction::Litchi(Action::StartRecording) => ("startRecord", vec![]),
+            WpmlAction::Litchi(Action::StopRecording) => ("stopRecord", vec![]),
+
+            WpmlAction::Litchi(Action::RotateAircraft { .. }) => {
+                unreachable!("rotations are written with their direction")
+            }
+
+            WpmlAction::RotateYaw {
+                heading: degrees,
+                clockwise,
+            } => (
+                "rotateYaw",
+                vec![
+                    ("aircraftHeading", heading(*degrees).to_string()),
+                    (
+                        "aircraftPathMode",
+                        if *clockwise {
+                            "clockwise"
+                        } else {
+                            "counterClockwise"
+                        }
+                        .to_owned(),
+                    ),
+                ],
+            ),
+
+            WpmlAction::Litchi(Action::TiltCamera { angle }) => {
+                return self.action(&WpmlAction::TiltCamera(*angle as f64))
+            }
+
+            WpmlAction::TiltCamera(pitch) => (
+                "gimbalRotate",
+                vec![
+                    ("gimbalHeadingYawBase", "aircraft".to_owned()),
+                    ("gimbalRotateMode", "absoluteAngle".to_owned()),
+                    ("gimbalPitchRotateEnable", "1".to_owned()),
+                    ("gimbalPitchRotateAngle", pitch.to_string()),
+                    ("gimbalRollRotateEnable", "0".to_owned()),
+                    ("gimbalRollRotateAngle", "0".to_owned()),
+                    ("gimbalYawRotateEnable", "0".to_owned()),
+                    ("gimbalYawRotateAngle", "0".to_owned()),
+                    ("gimbalRotateTimeEnable", "0".to_owned()),
+                    ("gimbalRotateTime", "0".to_owned()),
+                ],
+            ),
+
+            WpmlAction::TiltCameraEvenly(pitch) => (
+                "gimbalEvenlyRotate",
+                vec![("gimbalPitchRotateAngle", pitch.to_string())],
+            ),
+        };
+
+        self.text("wpml:actionActuatorFunc", function)?;
+
+        self.start("wpml:actionActuatorFuncParam", &[])?;
+
+        for (name, value) in parameters {
+            self.text(&format!("wpml:{}", name), &value)?;
+        }
+
+        self.text("wpml:payloadPositionIndex", PAYLOAD_POSITION)?;
+        self.end("wpml:actionActuatorFuncParam")?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Read};
+
+    use quick_xml::{events::Event, Reader};
+
+    use crate::litchi::{
+        csv::{parse, MISSION},
+        Action,
+    };
+
+    use super::{Aircraft, Mission};
+
+    /// Text of the elements of `xml`, by their path from the root, e.g. `kml/Document`.
+    fn elements(xml: &str) -> Vec<(String, String)> {
+        let mut reader = Reader::from_str(xml);
+        reader.trim_text(true);
+
+        let (mut path, mut elements, mut buffer) = (vec![], vec![], vec![]);
+
+        loop {
+            match reader.read_event(&mut buffer).unwrap() {
+                Event::Start(start) => {
+                    path.push(String::from_utf8_lossy(start.name()).into_owned())
+                }
+                Event::End(_) => {
+                    path.pop();
+                }
+                Event::Text(text) => {
+                    elements.push((path.join("/"), text.unescape_and_decode(&reader).unwrap()))
+                }
+                Event::Eof => break,
+                _ => (),
+            }
+
+            buffer.clear();
+        }
+
+        assert!(path.is_empty());
+
+        elements
+    }
+
+    /// Text of the elements at `path`, in the order of the document.
+    fn texts<'e>(elements: &'e [(String, String)], path: &str) -> Vec<&'e str> {
+        elements
+            .iter()
+            .filter(|(p, _)| p == path)
+            .map(|(_, text)| text.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn records_become_waylines() {
+        let mut records = parse(MISSION.as_bytes());
+
+        // Looking north east at their points of interest, at first
+        records[0]
+            .actions
+            .push(Action::RotateAircraft { angle: 350 });
+        records[1]
+            .actions
+            .push(Action::RotateAircraft { angle: 90 });
+
+        let mission = Mission::from(records.as_slice());
+
+        // The point of interest of the second waypoint is above ground.
+        assert_eq!(mission.losses().len(), 1);
+
+        let mut kmz = Cursor::new(vec![]);
+
+        mission.to_kmz(Aircraft::M3t, &mut kmz).unwrap();
+
+        let mut archive = zip::ZipArchive::new(kmz).unwrap();
+
+        let mut read = |name| {
+            let mut xml = String::new();
+
+            archive
+                .by_name(name)
+                .unwrap()
+                .read_to_string(&mut xml)
+                .unwrap();
+
+            elements(&xml)
+        };
+
+        let template = read("wpmz/template.kml");
+        let waylines = read("wpmz/waylines.wpml");
+
+        for elements in [&template, &waylines] {
+            let config = |name| {
+                texts(
+                    elements,
+                    &format!("kml/Document/wpml:missionConfig/{}", name),
+                )
+            };
+
+            assert_eq!(config("wpml:finishAction"), ["noAction"]);
+            assert_eq!(config("wpml:takeOffSecurityHeight"), ["20"]);
+            assert_eq!(config("wpml:droneInfo/wpml:droneEnumValue"), ["77"]);
+            assert_eq!(config("wpml:droneInfo/wpml:droneSubEnumValue"), ["1"]);
+            assert_eq!(config("wpml:payloadInfo/wpml:payloadEnumValue"), ["67"]);
+        }
+
+        let placemark = |name: &str| {
+            texts(
+                &waylines,
+                &format!("kml/Document/Folder/Placemark/{}", name),
+            )
+        };
+        let heading = |name| placemark(&format!("wpml:waypointHeadingParam/{}", name));
+        let actions = |name| {
+            placemark(&format!(
+                "wpml:actionGroup/wpml:action/wpml:actionActuatorFuncParam/wpml:{}",
+                name
+            ))
+        };
+
+        assert_eq!(placemark("wpml:executeHeight"), ["30", "40"]);
+        assert_eq!(placemark("wpml:waypointSpeed"), ["7", "9"]);
+        assert_eq!(
+            heading("wpml:waypointHeadingMode"),
+            ["towardPOI", "towardPOI"]
+        );
+        assert_eq!(
+            heading("wpml:waypointPoiPoint"),
+            ["48.857,2.353,10", "48.858,2.354,15"]
+        );
+        assert_eq!(
+            placemark("wpml:waypointTurnParam/wpml:waypointTurnMode"),
+            ["coordinateTurn", "toPointAndStopWithDiscontinuityCurvature"]
+        );
+
+        assert_eq!(
+            placemark("wpml:actionGroup/wpml:actionTrigger/wpml:actionTriggerType"),
+            [
+                "reachPoint",
+                "betweenAdjacentPoints",
+                "reachPoint",
+                "multipleTiming"
+            ]
+        );
+        assert_eq!(
+            placemark("wpml:actionGroup/wpml:action/wpml:actionActuatorFunc"),
+            [
+                "startRecord",
+                "hover",
+                "rotateYaw",
+                "gimbalEvenlyRotate",
+                "stopRecord",
+                "gimbalRotate",
+                "rotateYaw",
+                "takePhoto"
+            ]
+        );
+        assert_eq!(actions("hoverTime"), ["2"]);
+        assert_eq!(actions("gimbalPitchRotateAngle"), ["-60", "-45"]);
+        assert_eq!(actions("aircraftHeading"), ["-10", "90"]);
+        assert_eq!(
+            actions("aircraftPathMode"),
+            ["counterClockwise", "clockwise"]
+        );
+    }
+}